@@ -0,0 +1,93 @@
+//! Runs the dungeon without a browser.
+//!
+//! `cargo run --example headless --no-default-features -- <seed> <moves>` where moves is a string
+//! of `u`, `d`, `l` and `r`, prints the map and the player state after each move.
+
+use std::{collections::HashMap, env};
+
+use rusty_roguelike::game::{
+    camera::Camera,
+    common::UIEvent,
+    components::{damageable::Damageable, player_controlled::PlayerControlled, rendered::Render},
+    simulation::Simulation,
+    tile_map::TileMap,
+    world::{WorldParameters, WorldPosition, WorldTime},
+};
+use specs::prelude::*;
+
+fn print_map(simulation: &Simulation) {
+    let camera = simulation.world.read_resource::<Camera>();
+    let tile_map = simulation.world.read_resource::<TileMap>();
+    let world_position = simulation.world.read_storage::<WorldPosition>();
+    let render = simulation.world.read_storage::<Render>();
+
+    let mut glyphs: HashMap<WorldPosition, &Render> = HashMap::new();
+    for (pos, render) in (&world_position, &render).join() {
+        let replace = glyphs
+            .get(pos)
+            .map(|current| current.z_layer <= render.z_layer)
+            .unwrap_or(true);
+        if replace {
+            glyphs.insert(*pos, render);
+        }
+    }
+
+    // Only what the camera shows, as levels are bigger than the screen
+    for y in camera.origin.y..camera.origin.y + camera.height {
+        let line: String = (camera.origin.x..camera.origin.x + camera.width)
+            .map(|x| {
+                let pos = WorldPosition { x, y };
+                glyphs
+                    .get(&pos)
+                    .and_then(|render| render.glyph)
+                    .or_else(|| tile_map.get(pos).and_then(|tile| tile.render().glyph))
+                    .unwrap_or(' ')
+            })
+            .collect();
+        println!("{}", line);
+    }
+}
+
+fn print_player(simulation: &Simulation) {
+    let world_position = simulation.world.read_storage::<WorldPosition>();
+    let player_controlled = simulation.world.read_storage::<PlayerControlled>();
+    let damageable = simulation.world.read_storage::<Damageable>();
+    let world_time = simulation.world.read_resource::<WorldTime>();
+
+    for (_, pos, damageable) in (&player_controlled, &world_position, &damageable).join() {
+        println!(
+            "tick {}: player at ({}, {}) with {}/{} health",
+            world_time.tick, pos.x, pos.y, damageable.health, damageable.max_health
+        );
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let seed = args.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let moves = args.next().unwrap_or_default();
+
+    let mut simulation = Simulation::new(
+        seed,
+        WorldParameters {
+            width: 100,
+            height: 100,
+        },
+    );
+    simulation.tick(vec![]);
+    print_map(&simulation);
+    print_player(&simulation);
+
+    for c in moves.chars() {
+        let event = match c {
+            'u' => UIEvent::Up,
+            'd' => UIEvent::Down,
+            'l' => UIEvent::Left,
+            'r' => UIEvent::Right,
+            _ => continue,
+        };
+        simulation.tick(vec![event]);
+        print_player(&simulation);
+    }
+    print_map(&simulation);
+}
@@ -3,6 +3,22 @@
 // will work here one day as well!
 const rust = import('./pkg');
 
+const seed = new URLSearchParams(window.location.search).get('seed');
+
+const SAVE_KEY = 'rusty-roguelike-save';
+
 rust
-  .then(m => m.start())
+  .then(m => {
+    // Exposed for `export_replay` and `watch_replay` in the browser console
+    window.rustyRoguelike = m;
+    window.addEventListener('beforeunload', () => {
+      const save = m.save_game();
+      if (save !== undefined) {
+        localStorage.setItem(SAVE_KEY, save);
+      }
+    });
+    // An explicit seed starts a fresh dungeon instead of resuming the saved one
+    const save = seed === null ? localStorage.getItem(SAVE_KEY) : null;
+    return m.start(seed === null ? undefined : parseInt(seed, 10), save === null ? undefined : save);
+  })
   .catch(console.error);
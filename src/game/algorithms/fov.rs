@@ -0,0 +1,197 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+use crate::game::world::WorldPosition;
+
+/// A slope as a fraction, `column / depth`, kept exact so that the same cells are always lit.
+#[derive(Clone, Copy)]
+struct Slope {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Slope {
+    fn new(numerator: i64, denominator: i64) -> Slope {
+        Slope {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// The slope going through the edge of the cell closest to the start of the row.
+    fn of_cell(column: i64, depth: i64) -> Slope {
+        Slope::new(2 * column - 1, 2 * depth)
+    }
+
+    /// Compares `column` with where the slope crosses the row `depth`.
+    fn compare(&self, column: i64, depth: i64) -> Ordering {
+        (column * self.denominator).cmp(&(depth * self.numerator))
+    }
+}
+
+/// A row of cells at the same distance from the origin, within the part of a quadrant still lit.
+#[derive(Clone, Copy)]
+struct Row {
+    depth: i64,
+    start: Slope,
+    end: Slope,
+}
+
+impl Row {
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+
+    /// The first and last columns the row covers, cells whose center is right on a slope
+    /// being counted in.
+    fn columns(&self) -> std::ops::RangeInclusive<i64> {
+        let first = (2 * self.depth * self.start.numerator + self.start.denominator)
+            .div_euclid(2 * self.start.denominator);
+        let last = -(-(2 * self.depth * self.end.numerator - self.end.denominator))
+            .div_euclid(2 * self.end.denominator);
+        first..=last
+    }
+
+    /// Whether the center of the cell is inside the lit part of the row, rather than only a corner.
+    fn is_symmetric(&self, column: i64) -> bool {
+        self.start.compare(column, self.depth) != Ordering::Less
+            && self.end.compare(column, self.depth) != Ordering::Greater
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Quadrant {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Quadrant {
+    const ALL: [Quadrant; 4] = [
+        Quadrant::North,
+        Quadrant::East,
+        Quadrant::South,
+        Quadrant::West,
+    ];
+
+    /// Where the cell at `depth` away from the origin and `column` across ends up, if it is on the
+    /// world at all.
+    fn transform(&self, origin: WorldPosition, depth: i64, column: i64) -> Option<WorldPosition> {
+        let (dx, dy) = match self {
+            Quadrant::North => (column, -depth),
+            Quadrant::East => (depth, column),
+            Quadrant::South => (column, depth),
+            Quadrant::West => (-depth, column),
+        };
+        Some(WorldPosition {
+            x: origin.x.checked_add_signed(dx)?,
+            y: origin.y.checked_add_signed(dy)?,
+        })
+    }
+}
+
+/// The cells seen from `origin` at most `radius` cells away, using symmetric shadowcasting.
+///
+/// Any cell seen from another sees it back, and opaque cells are seen along with what's in front
+/// of them so that walls show up. Cells off the world are opaque.
+pub fn field_of_view(
+    origin: WorldPosition,
+    radius: u64,
+    is_opaque: impl Fn(WorldPosition) -> bool,
+) -> HashSet<WorldPosition> {
+    let radius = radius as i64;
+    let mut visible = HashSet::from([origin]);
+
+    for quadrant in Quadrant::ALL {
+        let cell = |depth: i64, column: i64| quadrant.transform(origin, depth, column);
+        let blocks = |position: Option<WorldPosition>| position.is_none_or(&is_opaque);
+
+        let mut rows = vec![Row {
+            depth: 1,
+            start: Slope::new(-1, 1),
+            end: Slope::new(1, 1),
+        }];
+        while let Some(mut row) = rows.pop() {
+            if row.depth > radius {
+                continue;
+            }
+            let mut previous_blocked = None;
+            for column in row.columns() {
+                let position = cell(row.depth, column);
+                let blocked = blocks(position);
+                let in_range = column * column + row.depth * row.depth <= radius * radius;
+                if let Some(position) = position {
+                    if in_range && (blocked || row.is_symmetric(column)) {
+                        visible.insert(position);
+                    }
+                }
+                if previous_blocked == Some(true) && !blocked {
+                    row.start = Slope::of_cell(column, row.depth);
+                }
+                if previous_blocked == Some(false) && blocked {
+                    let mut next_row = row.next();
+                    next_row.end = Slope::of_cell(column, row.depth);
+                    rows.push(next_row);
+                }
+                previous_blocked = Some(blocked);
+            }
+            if previous_blocked == Some(false) {
+                rows.push(row.next());
+            }
+        }
+    }
+
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::game::{random::RandomGenerator, world::WorldPosition};
+
+    use super::field_of_view;
+
+    #[test]
+    fn walls_are_seen_and_cast_shadows() {
+        let pillar = WorldPosition { x: 5, y: 3 };
+        let visible = field_of_view(WorldPosition { x: 5, y: 5 }, 10, |position| {
+            position == pillar || position.x >= 10
+        });
+
+        assert!(visible.contains(&pillar));
+        assert!(!visible.contains(&WorldPosition { x: 5, y: 2 }));
+        assert!(!visible.contains(&WorldPosition { x: 5, y: 0 }));
+        assert!(visible.contains(&WorldPosition { x: 4, y: 2 }));
+        assert!(visible.contains(&WorldPosition { x: 10, y: 5 }));
+        assert!(!visible.contains(&WorldPosition { x: 11, y: 5 }));
+        assert!(visible.contains(&WorldPosition { x: 5, y: 15 }));
+        assert!(!visible.contains(&WorldPosition { x: 5, y: 16 }));
+    }
+
+    #[test]
+    fn sight_goes_both_ways() {
+        for seed in 0..5 {
+            let mut rng = RandomGenerator::from_seed(seed);
+            let walls: HashSet<WorldPosition> = (0..12)
+                .flat_map(|x| (0..12).map(move |y| WorldPosition { x, y }))
+                .filter(|_| rng.next_f64() < 0.25)
+                .collect();
+            let is_opaque = |position: WorldPosition| walls.contains(&position);
+            let floor: Vec<WorldPosition> = (0..12)
+                .flat_map(|x| (0..12).map(move |y| WorldPosition { x, y }))
+                .filter(|position| !is_opaque(*position))
+                .collect();
+
+            for from in &floor {
+                let visible = field_of_view(*from, 8, is_opaque);
+                for to in floor.iter().filter(|to| visible.contains(to)) {
+                    assert!(field_of_view(*to, 8, is_opaque).contains(from));
+                }
+            }
+        }
+    }
+}
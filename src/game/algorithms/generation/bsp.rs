@@ -0,0 +1,183 @@
+use crate::game::random::RandomGenerator;
+
+use super::{LevelGenerator, LevelLayout, Rect};
+
+/// Splits the level in two over and over (binary space partitioning), puts a room in each part
+/// and joins the rooms with corridors.
+pub struct Bsp {
+    pub min_room_size: u64,
+    /// How many times the level is split at most, so there are up to `2^max_splits` rooms.
+    pub max_splits: u32,
+}
+
+impl Default for Bsp {
+    fn default() -> Self {
+        Bsp {
+            min_room_size: 3,
+            max_splits: 4,
+        }
+    }
+}
+
+impl Bsp {
+    /// Leaves are kept big enough for a room and the wall around it.
+    fn min_leaf_size(&self) -> u64 {
+        self.min_room_size + 2
+    }
+
+    /// Leaves come out in tree order, so consecutive leaves are next to each other.
+    fn split(
+        &self,
+        area: Rect,
+        splits_left: u32,
+        rng: &mut RandomGenerator,
+        leaves: &mut Vec<Rect>,
+    ) {
+        let min_leaf_size = self.min_leaf_size();
+        let can_split_width = area.width >= min_leaf_size * 2;
+        let can_split_height = area.height >= min_leaf_size * 2;
+
+        let split_width = match (can_split_width, can_split_height) {
+            _ if splits_left == 0 => None,
+            (true, true) => Some(rng.random_in_range(0, 1) == 0),
+            (true, false) => Some(true),
+            (false, true) => Some(false),
+            (false, false) => None,
+        };
+
+        match split_width {
+            Some(true) => {
+                let width = rng.random_in_range(min_leaf_size, area.width - min_leaf_size);
+                let (left, right) = (
+                    Rect { width, ..area },
+                    Rect {
+                        x: area.x + width,
+                        width: area.width - width,
+                        ..area
+                    },
+                );
+                self.split(left, splits_left - 1, rng, leaves);
+                self.split(right, splits_left - 1, rng, leaves);
+            }
+            Some(false) => {
+                let height = rng.random_in_range(min_leaf_size, area.height - min_leaf_size);
+                let (top, bottom) = (
+                    Rect { height, ..area },
+                    Rect {
+                        y: area.y + height,
+                        height: area.height - height,
+                        ..area
+                    },
+                );
+                self.split(top, splits_left - 1, rng, leaves);
+                self.split(bottom, splits_left - 1, rng, leaves);
+            }
+            None => leaves.push(area),
+        }
+    }
+
+    /// A random room inside the leaf, keeping a wall on every side when there is space for it.
+    fn room_in(&self, leaf: Rect, rng: &mut RandomGenerator) -> Rect {
+        let (x, width) = self.span_in(leaf.x, leaf.width, rng);
+        let (y, height) = self.span_in(leaf.y, leaf.height, rng);
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn span_in(&self, start: u64, length: u64, rng: &mut RandomGenerator) -> (u64, u64) {
+        let margin = if length >= 3 { 1 } else { 0 };
+        let max_length = length - margin * 2;
+        let room_length = rng.random_in_range(self.min_room_size.min(max_length), max_length);
+        let room_start = rng.random_in_range(start + margin, start + length - margin - room_length);
+        (room_start, room_length)
+    }
+}
+
+impl LevelGenerator for Bsp {
+    fn generate(&self, width: u64, height: u64, rng: &mut RandomGenerator) -> LevelLayout {
+        let mut layout = LevelLayout::new(width, height);
+
+        let mut leaves = vec![];
+        let whole_level = Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        self.split(whole_level, self.max_splits, rng, &mut leaves);
+
+        for leaf in leaves {
+            let room = self.room_in(leaf, rng);
+            for position in room.positions() {
+                layout.carve(position);
+            }
+            layout.rooms.push(room);
+        }
+
+        let rooms = layout.rooms.clone();
+        for pair in rooms.windows(2) {
+            let x_first = rng.random_in_range(0, 1) == 0;
+            layout.carve_tunnel(pair[0].center(), pair[1].center(), x_first);
+        }
+
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        algorithms::{
+            generation::LevelGenerator,
+            pathfinding::{a_star, Obstacles},
+        },
+        random::RandomGenerator,
+        world::WorldPosition,
+    };
+
+    use super::Bsp;
+
+    #[test]
+    fn rooms_are_separate_and_connected() {
+        for seed in 0..20 {
+            let mut rng = RandomGenerator::from_seed(seed);
+            let layout = Bsp::default().generate(40, 25, &mut rng);
+
+            assert!(layout.rooms.len() > 1);
+            for (i, room) in layout.rooms.iter().enumerate() {
+                assert!(room.x + room.width <= 40 && room.y + room.height <= 25);
+                assert!(room.positions().all(|position| layout.is_floor(position)));
+                assert!(layout.rooms[i + 1..]
+                    .iter()
+                    .all(|other| !room.intersects(other)));
+            }
+
+            let mut obstacles = Obstacles::new(40, 25);
+            for x in 0..40 {
+                for y in 0..25 {
+                    let position = WorldPosition { x, y };
+                    if !layout.is_floor(position) {
+                        obstacles.block(position);
+                    }
+                }
+            }
+            let first = layout.rooms[0].center();
+            for room in &layout.rooms[1..] {
+                assert!(a_star(&obstacles, first, room.center()).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn tiny_levels_still_get_a_room() {
+        let mut rng = RandomGenerator::from_seed(1);
+        let layout = Bsp::default().generate(2, 2, &mut rng);
+
+        assert_eq!(layout.rooms.len(), 1);
+        assert!(!layout.floor_positions().is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::game::{random::RandomGenerator, world::WorldPosition};
+
+use super::{LevelGenerator, LevelLayout};
+
+/// Starts from random noise and smooths it into caves: a cell turns to rock when most of the cells
+/// around it are rock, and to floor otherwise. Caves left apart get tunnelled together.
+pub struct CellularAutomata {
+    /// Chance for each cell to start as rock.
+    pub rock_chance: f64,
+    pub iterations: usize,
+    /// A cell turns to rock when at least this many of the 9 cells around and including it are.
+    pub rock_threshold: usize,
+}
+
+impl Default for CellularAutomata {
+    fn default() -> Self {
+        CellularAutomata {
+            rock_chance: 0.45,
+            iterations: 4,
+            rock_threshold: 5,
+        }
+    }
+}
+
+impl CellularAutomata {
+    /// Cells outside the level count as rock, so caves don't stick to the edges.
+    fn rock_around(layout: &LevelLayout, x: u64, y: u64) -> usize {
+        let mut rock = 0;
+        for dx in -1i64..=1 {
+            for dy in -1i64..=1 {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                let inside =
+                    nx >= 0 && ny >= 0 && (nx as u64) < layout.width && (ny as u64) < layout.height;
+                let is_floor = inside
+                    && layout.is_floor(WorldPosition {
+                        x: nx as u64,
+                        y: ny as u64,
+                    });
+                if !is_floor {
+                    rock += 1;
+                }
+            }
+        }
+        rock
+    }
+}
+
+impl LevelGenerator for CellularAutomata {
+    fn generate(&self, width: u64, height: u64, rng: &mut RandomGenerator) -> LevelLayout {
+        let mut layout = LevelLayout::new(width, height);
+        for x in 0..width {
+            for y in 0..height {
+                if rng.next_f64() >= self.rock_chance {
+                    layout.carve(WorldPosition { x, y });
+                }
+            }
+        }
+
+        for _ in 0..self.iterations {
+            let mut next = LevelLayout::new(width, height);
+            for x in 0..width {
+                for y in 0..height {
+                    if CellularAutomata::rock_around(&layout, x, y) < self.rock_threshold {
+                        next.carve(WorldPosition { x, y });
+                    }
+                }
+            }
+            layout = next;
+        }
+
+        layout.connect_regions();
+        if layout.floor_positions().is_empty() {
+            // Levels too small for caves to form still need somewhere to stand
+            layout.carve(WorldPosition {
+                x: width / 2,
+                y: height / 2,
+            });
+        }
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{algorithms::generation::LevelGenerator, random::RandomGenerator};
+
+    use super::CellularAutomata;
+
+    #[test]
+    fn caves_are_a_single_connected_region() {
+        for seed in 0..20 {
+            let mut rng = RandomGenerator::from_seed(seed);
+            let layout = CellularAutomata::default().generate(40, 25, &mut rng);
+
+            let regions = layout.regions();
+            assert_eq!(regions.len(), 1);
+            // Big enough to play in, but not an open field
+            assert!(regions[0].len() > 40 * 25 / 5);
+            assert!(regions[0].len() < 40 * 25);
+        }
+    }
+
+    #[test]
+    fn tiny_levels_still_have_floor() {
+        let mut rng = RandomGenerator::from_seed(1);
+        let layout = CellularAutomata::default().generate(2, 2, &mut rng);
+
+        assert_eq!(layout.regions().len(), 1);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::game::{components::movable::Direction, random::RandomGenerator, world::WorldPosition};
+
+use super::{LevelGenerator, LevelLayout};
+
+/// Walkers starting at random positions and carving wherever they stumble, leaving blobby caves.
+///
+/// Walkers which never cross paths leave caves of their own, which get tunnelled together.
+pub struct DrunkardWalk {
+    pub walkers: usize,
+    pub steps: usize,
+}
+
+impl Default for DrunkardWalk {
+    fn default() -> Self {
+        DrunkardWalk {
+            walkers: 3,
+            steps: 1000,
+        }
+    }
+}
+
+impl LevelGenerator for DrunkardWalk {
+    fn generate(&self, width: u64, height: u64, rng: &mut RandomGenerator) -> LevelLayout {
+        let mut layout = LevelLayout::new(width, height);
+        let mut walkers: Vec<WorldPosition> = vec![];
+
+        for _ in 0..self.walkers {
+            let x = rng.random_in_range(0, width - 1);
+            let y = rng.random_in_range(0, height - 1);
+            let position = WorldPosition { x, y };
+            walkers.push(position);
+            layout.carve(position);
+        }
+
+        for _ in 0..self.steps {
+            for position in walkers.iter_mut() {
+                *position = position.moved(Direction::random(rng), width, height);
+                layout.carve(*position);
+            }
+        }
+
+        layout.connect_regions();
+        layout
+    }
+}
@@ -0,0 +1,236 @@
+pub mod bsp;
+pub mod cellular_automata;
+pub mod drunkard_walk;
+pub mod vaults;
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{random::RandomGenerator, world::WorldPosition};
+
+use self::{bsp::Bsp, cellular_automata::CellularAutomata, drunkard_walk::DrunkardWalk};
+
+/// A rectangle of cells, such as a room.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u64,
+    pub y: u64,
+    pub width: u64,
+    pub height: u64,
+}
+
+impl Rect {
+    pub fn center(&self) -> WorldPosition {
+        WorldPosition {
+            x: self.x + self.width / 2,
+            y: self.y + self.height / 2,
+        }
+    }
+
+    pub fn contains(&self, world_position: WorldPosition) -> bool {
+        (self.x..self.x + self.width).contains(&world_position.x)
+            && (self.y..self.y + self.height).contains(&world_position.y)
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Every cell of the rectangle, column by column.
+    pub fn positions(&self) -> impl Iterator<Item = WorldPosition> + '_ {
+        (self.x..self.x + self.width)
+            .flat_map(move |x| (self.y..self.y + self.height).map(move |y| WorldPosition { x, y }))
+    }
+}
+
+/// Which cells of a level are floor, and where its rooms are.
+pub struct LevelLayout {
+    pub width: u64,
+    pub height: u64,
+    floor: Vec<bool>,
+    /// Empty when the generator doesn't make rooms.
+    pub rooms: Vec<Rect>,
+    /// Where vaults were stamped, including the floor around them.
+    pub vaults: Vec<Rect>,
+    /// The templates spawned on each cell of the vaults, in place of the terrain.
+    pub vault_cells: HashMap<WorldPosition, Vec<String>>,
+}
+
+impl LevelLayout {
+    /// A level made of solid rock.
+    pub fn new(width: u64, height: u64) -> LevelLayout {
+        LevelLayout {
+            width,
+            height,
+            floor: vec![false; (width * height) as usize],
+            rooms: vec![],
+            vaults: vec![],
+            vault_cells: HashMap::new(),
+        }
+    }
+
+    fn index(&self, world_position: WorldPosition) -> Option<usize> {
+        if world_position.x < self.width && world_position.y < self.height {
+            Some((world_position.y * self.width + world_position.x) as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn carve(&mut self, world_position: WorldPosition) {
+        if let Some(index) = self.index(world_position) {
+            self.floor[index] = true;
+        }
+    }
+
+    pub fn is_floor(&self, world_position: WorldPosition) -> bool {
+        self.index(world_position)
+            .is_some_and(|index| self.floor[index])
+    }
+
+    pub fn fill(&mut self, world_position: WorldPosition) {
+        if let Some(index) = self.index(world_position) {
+            self.floor[index] = false;
+        }
+    }
+
+    /// Groups of floor cells connected to each other, largest first.
+    pub fn regions(&self) -> Vec<Vec<WorldPosition>> {
+        let mut visited = vec![false; self.floor.len()];
+        let mut regions = vec![];
+        for start in self.floor_positions() {
+            let start_index = self.index(start).unwrap();
+            if visited[start_index] {
+                continue;
+            }
+            visited[start_index] = true;
+            let mut region = vec![];
+            let mut queue = VecDeque::from([start]);
+            while let Some(position) = queue.pop_front() {
+                region.push(position);
+                let neighbours = [
+                    (position.x + 1, position.y),
+                    (position.x.wrapping_sub(1), position.y),
+                    (position.x, position.y + 1),
+                    (position.x, position.y.wrapping_sub(1)),
+                ];
+                for (x, y) in neighbours {
+                    let neighbour = WorldPosition { x, y };
+                    if let Some(index) = self.index(neighbour) {
+                        if self.floor[index] && !visited[index] {
+                            visited[index] = true;
+                            queue.push_back(neighbour);
+                        }
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        // Stable, so ties keep the column by column order
+        regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+        regions
+    }
+
+    /// Carves an L-shaped tunnel, going along the x axis first or last.
+    pub fn carve_tunnel(&mut self, from: WorldPosition, to: WorldPosition, x_first: bool) {
+        let corner = if x_first {
+            WorldPosition { x: to.x, y: from.y }
+        } else {
+            WorldPosition { x: from.x, y: to.y }
+        };
+        for (a, b) in [(from, corner), (corner, to)] {
+            for x in a.x.min(b.x)..=a.x.max(b.x) {
+                for y in a.y.min(b.y)..=a.y.max(b.y) {
+                    self.carve(WorldPosition { x, y });
+                }
+            }
+        }
+    }
+
+    /// Tunnels from every other region to the closest cell of the largest one, so anything
+    /// placed on the floor can reach anything else.
+    pub fn connect_regions(&mut self) {
+        let regions = self.regions();
+        let Some((largest, others)) = regions.split_first() else {
+            return;
+        };
+        for region in others {
+            let closest = region
+                .iter()
+                .flat_map(|from| largest.iter().map(move |to| (*from, *to)))
+                .min_by_key(|(from, to)| from.x.abs_diff(to.x) + from.y.abs_diff(to.y));
+            if let Some((from, to)) = closest {
+                self.carve_tunnel(from, to, true);
+            }
+        }
+    }
+
+    /// Every floor cell, column by column so the order only depends on the layout.
+    pub fn floor_positions(&self) -> Vec<WorldPosition> {
+        Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        }
+        .positions()
+        .filter(|world_position| self.is_floor(*world_position))
+        .collect()
+    }
+}
+
+/// Carves the terrain of a level.
+pub trait LevelGenerator {
+    fn generate(&self, width: u64, height: u64, rng: &mut RandomGenerator) -> LevelLayout;
+}
+
+/// The generators a floor can be built with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GeneratorKind {
+    DrunkardWalk,
+    Bsp,
+    CellularAutomata,
+}
+
+impl GeneratorKind {
+    pub const ALL: [GeneratorKind; 3] = [
+        GeneratorKind::DrunkardWalk,
+        GeneratorKind::Bsp,
+        GeneratorKind::CellularAutomata,
+    ];
+
+    pub fn generator(&self) -> Box<dyn LevelGenerator> {
+        match self {
+            GeneratorKind::DrunkardWalk => Box::<DrunkardWalk>::default(),
+            GeneratorKind::Bsp => Box::<Bsp>::default(),
+            GeneratorKind::CellularAutomata => Box::<CellularAutomata>::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::world::WorldPosition;
+
+    use super::LevelLayout;
+
+    #[test]
+    fn regions_are_tunnelled_together() {
+        let mut layout = LevelLayout::new(7, 3);
+        for x in [0, 1, 3, 4, 5] {
+            layout.carve(WorldPosition { x, y: 1 });
+        }
+        layout.carve(WorldPosition { x: 6, y: 0 });
+        assert_eq!(layout.regions().len(), 3);
+
+        layout.connect_regions();
+
+        assert_eq!(layout.regions().len(), 1);
+        // Only the cells in between were dug
+        assert_eq!(layout.floor_positions().len(), 8);
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::random::RandomGenerator;
+
+use super::{LevelLayout, Rect};
+
+/// A hand-made set piece, drawn in ASCII.
+///
+/// Every character of `rows` is looked up in `legend`, which lists the templates spawned on that
+/// cell, terrain included. The border should have at least one opening, since the vault is only
+/// reachable through the floor laid around it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Vault {
+    pub rows: Vec<String>,
+    pub legend: HashMap<char, Vec<String>>,
+    /// Chance for the vault to show up on a floor where it is available.
+    pub chance: f64,
+    pub min_depth: u32,
+    /// The vault keeps being available all the way down when not set.
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+}
+
+impl Vault {
+    pub fn is_available_at(&self, depth: u32) -> bool {
+        self.min_depth <= depth && self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+
+    /// Why the vault can't be stamped, if anything is wrong with it.
+    pub fn problem(&self) -> Option<String> {
+        let width = self
+            .rows
+            .first()
+            .map(|row| row.chars().count())
+            .unwrap_or(0);
+        if width == 0 {
+            return Some("it is empty".to_owned());
+        }
+        if self.rows.iter().any(|row| row.chars().count() != width) {
+            return Some("its rows are not all the same length".to_owned());
+        }
+        self.rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .find(|c| !self.legend.contains_key(c))
+            .map(|c| format!("'{}' is missing from its legend", c))
+    }
+
+    /// The vault's cells turned a quarter clockwise `quarter_turns` times, then mirrored
+    /// left to right.
+    pub fn cells(&self, quarter_turns: u8, mirrored: bool) -> Vec<Vec<char>> {
+        let mut cells: Vec<Vec<char>> = self.rows.iter().map(|row| row.chars().collect()).collect();
+        for _ in 0..quarter_turns % 4 {
+            let height = cells.len();
+            let width = cells[0].len();
+            cells = (0..width)
+                .map(|x| (0..height).rev().map(|y| cells[y][x]).collect())
+                .collect();
+        }
+        if mirrored {
+            for row in cells.iter_mut() {
+                row.reverse();
+            }
+        }
+        cells
+    }
+}
+
+/// Rolls for every vault available at that depth, and stamps those that come up where they fit.
+pub fn stamp_vaults<'a>(
+    layout: &mut LevelLayout,
+    vaults: impl Iterator<Item = &'a Vault>,
+    depth: u32,
+    rng: &mut RandomGenerator,
+) {
+    for vault in vaults {
+        if !vault.is_available_at(depth) || rng.next_f64() >= vault.chance {
+            continue;
+        }
+        let quarter_turns = rng.random_in_range(0, 3) as u8;
+        let mirrored = rng.random_in_range(0, 1) == 1;
+        stamp(layout, vault, &vault.cells(quarter_turns, mirrored), rng);
+    }
+}
+
+/// Surrounds the vault with floor so that it can be reached, and so that walking around it stays
+/// possible wherever it was placed. Any tunnel dug to join that floor to the rest of the level
+/// then stops at it, without going through the vault.
+fn stamp(layout: &mut LevelLayout, vault: &Vault, cells: &[Vec<char>], rng: &mut RandomGenerator) {
+    let (width, height) = (cells[0].len() as u64, cells.len() as u64);
+    if width + 2 > layout.width || height + 2 > layout.height {
+        return;
+    }
+
+    let surroundings = |x: u64, y: u64| Rect {
+        x,
+        y,
+        width: width + 2,
+        height: height + 2,
+    };
+    let candidates: Vec<Rect> = (0..=layout.width - width - 2)
+        .flat_map(|x| (0..=layout.height - height - 2).map(move |y| surroundings(x, y)))
+        .filter(|area| !layout.vaults.iter().any(|vault| vault.intersects(area)))
+        .collect();
+    // Open ground is taken over as is, elsewhere the floor around the vault is dug out
+    let open: Vec<Rect> = candidates
+        .iter()
+        .filter(|area| area.positions().all(|position| layout.is_floor(position)))
+        .copied()
+        .collect();
+    let Some(area) = rng
+        .random_in_vec(&open)
+        .or_else(|| rng.random_in_vec(&candidates))
+        .copied()
+    else {
+        return;
+    };
+
+    let inside = Rect {
+        x: area.x + 1,
+        y: area.y + 1,
+        width,
+        height,
+    };
+    for position in area.positions() {
+        if inside.contains(position) {
+            layout.fill(position);
+            let c = cells[(position.y - inside.y) as usize][(position.x - inside.x) as usize];
+            layout
+                .vault_cells
+                .insert(position, vault.legend[&c].clone());
+        } else {
+            layout.carve(position);
+        }
+    }
+    layout.vaults.push(area);
+    layout.connect_regions();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::game::{
+        algorithms::generation::{LevelLayout, Rect},
+        random::RandomGenerator,
+        world::WorldPosition,
+    };
+
+    use super::{stamp_vaults, Vault};
+
+    fn vault() -> Vault {
+        Vault {
+            rows: vec!["##".to_owned(), "#$".to_owned(), "#.".to_owned()],
+            legend: HashMap::from([
+                ('#', vec!["stone_wall".to_owned()]),
+                ('.', vec!["floor".to_owned()]),
+                ('$', vec!["floor".to_owned(), "gold".to_owned()]),
+            ]),
+            chance: 1.0,
+            min_depth: 2,
+            max_depth: None,
+        }
+    }
+
+    #[test]
+    fn vaults_turn_and_mirror() {
+        let vault = vault();
+        let as_rows = |cells: Vec<Vec<char>>| -> Vec<String> {
+            cells
+                .into_iter()
+                .map(|row| row.into_iter().collect())
+                .collect()
+        };
+
+        assert_eq!(as_rows(vault.cells(0, false)), ["##", "#$", "#."]);
+        assert_eq!(as_rows(vault.cells(1, false)), ["###", ".$#"]);
+        assert_eq!(as_rows(vault.cells(2, true)), ["#.", "#$", "##"]);
+        assert_eq!(
+            as_rows(vault.cells(4, false)),
+            as_rows(vault.cells(0, false))
+        );
+    }
+
+    #[test]
+    fn vaults_are_stamped_within_reach() {
+        for seed in 0..10 {
+            let mut rng = RandomGenerator::from_seed(seed);
+            // Two caves apart, with rock in between
+            let mut layout = LevelLayout::new(12, 8);
+            for position in (Rect {
+                x: 0,
+                y: 0,
+                width: 3,
+                height: 8,
+            })
+            .positions()
+            .chain(
+                Rect {
+                    x: 9,
+                    y: 0,
+                    width: 3,
+                    height: 8,
+                }
+                .positions(),
+            ) {
+                layout.carve(position);
+            }
+
+            stamp_vaults(&mut layout, [vault()].iter(), 1, &mut rng);
+            assert!(layout.vault_cells.is_empty());
+
+            stamp_vaults(&mut layout, [vault()].iter(), 2, &mut rng);
+            assert_eq!(layout.vault_cells.len(), 6);
+            assert_eq!(
+                layout
+                    .vault_cells
+                    .values()
+                    .filter(|templates| templates.contains(&"gold".to_owned()))
+                    .count(),
+                1
+            );
+            assert!(layout
+                .vault_cells
+                .keys()
+                .all(|position| !layout.is_floor(*position)));
+            assert_eq!(layout.regions().len(), 1);
+            assert!(layout.is_floor(WorldPosition { x: 0, y: 0 }));
+        }
+    }
+}
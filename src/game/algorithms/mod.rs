@@ -1 +1,3 @@
-pub mod raycasting;
+pub mod fov;
+pub mod generation;
+pub mod pathfinding;
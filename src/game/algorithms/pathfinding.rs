@@ -0,0 +1,399 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use specs::Entity;
+
+use crate::game::{
+    components::movable::Direction, spatial_index::SpatialIndex, tile_map::TileMap,
+    world::WorldPosition,
+};
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Right,
+    Direction::Left,
+    Direction::Up,
+    Direction::Down,
+];
+
+/// How much further than the player's reach a fleeing creature aims for. Above 1, cornered
+/// creatures prefer slipping past the player over staying in a dead end.
+const FLEE_COEFFICIENT: (i32, i32) = (-6, 5);
+
+/// Cells that can't be walked through.
+#[derive(Clone)]
+pub struct Obstacles {
+    width: u64,
+    height: u64,
+    blocked: Vec<bool>,
+}
+
+impl Obstacles {
+    pub fn new(width: u64, height: u64) -> Obstacles {
+        Obstacles {
+            width,
+            height,
+            blocked: vec![false; (width * height) as usize],
+        }
+    }
+
+    /// Blocks the terrain that can't be walked on, and every cell holding an entity for which
+    /// `is_obstacle` holds.
+    pub fn from_terrain(
+        tile_map: &TileMap,
+        spatial_index: &SpatialIndex,
+        is_obstacle: impl Fn(Entity) -> bool,
+    ) -> Obstacles {
+        let mut obstacles = Obstacles::new(tile_map.width(), tile_map.height());
+        for world_position in tile_map.positions() {
+            if !tile_map.is_walkable(world_position) {
+                obstacles.block(world_position);
+            }
+        }
+        for (world_position, entities) in spatial_index.cells() {
+            if entities.iter().any(|entity| is_obstacle(*entity)) {
+                obstacles.block(world_position);
+            }
+        }
+        obstacles
+    }
+
+    pub fn block(&mut self, world_position: WorldPosition) {
+        if let Some(index) = self.index(world_position) {
+            self.blocked[index] = true;
+        }
+    }
+
+    /// Positions outside of the world are blocked.
+    pub fn is_blocked(&self, world_position: WorldPosition) -> bool {
+        self.index(world_position)
+            .is_none_or(|index| self.blocked[index])
+    }
+
+    /// Walkable positions one step away, with the direction leading to them.
+    pub fn neighbours(
+        &self,
+        world_position: WorldPosition,
+    ) -> impl Iterator<Item = (Direction, WorldPosition)> + '_ {
+        DIRECTIONS.iter().filter_map(move |direction| {
+            let neighbour = world_position.moved(*direction, self.width, self.height);
+            if neighbour == world_position || self.is_blocked(neighbour) {
+                None
+            } else {
+                Some((*direction, neighbour))
+            }
+        })
+    }
+
+    fn index(&self, world_position: WorldPosition) -> Option<usize> {
+        if world_position.x < self.width && world_position.y < self.height {
+            Some((world_position.y * self.width + world_position.x) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+fn manhattan_distance(from: WorldPosition, to: WorldPosition) -> u64 {
+    from.x.abs_diff(to.x) + from.y.abs_diff(to.y)
+}
+
+/// Shortest path from `start` to `goal`, excluding `start`.
+///
+/// The goal itself may be blocked, since it is usually a creature to reach rather than a cell to
+/// stand on.
+pub fn a_star(
+    obstacles: &Obstacles,
+    start: WorldPosition,
+    goal: WorldPosition,
+) -> Option<Vec<WorldPosition>> {
+    if start == goal {
+        return Some(vec![]);
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<WorldPosition, WorldPosition> = HashMap::new();
+    let mut costs: HashMap<WorldPosition, u64> = HashMap::new();
+
+    costs.insert(start, 0);
+    open.push(Reverse((manhattan_distance(start, goal), start)));
+
+    while let Some(Reverse((_, current))) = open.pop() {
+        if current == goal {
+            let mut path = vec![current];
+            let mut current = current;
+            while let Some(previous) = came_from.get(&current) {
+                if *previous == start {
+                    break;
+                }
+                path.push(*previous);
+                current = *previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let cost = costs[&current];
+        let candidates = DIRECTIONS.iter().filter_map(|direction| {
+            let neighbour = current.moved(*direction, obstacles.width, obstacles.height);
+            if neighbour != current && (neighbour == goal || !obstacles.is_blocked(neighbour)) {
+                Some(neighbour)
+            } else {
+                None
+            }
+        });
+        for neighbour in candidates {
+            let new_cost = cost + 1;
+            if costs.get(&neighbour).is_none_or(|c| new_cost < *c) {
+                costs.insert(neighbour, new_cost);
+                came_from.insert(neighbour, current);
+                open.push(Reverse((
+                    new_cost + manhattan_distance(neighbour, goal),
+                    neighbour,
+                )));
+            }
+        }
+    }
+
+    None
+}
+
+/// Distance from every walkable cell to the closest of a set of goals.
+///
+/// Creatures roll downhill to reach the goals, and uphill on the flee map to get away from them.
+#[derive(Clone)]
+pub struct DijkstraMap {
+    width: u64,
+    distances: Vec<Option<i32>>,
+}
+
+impl DijkstraMap {
+    pub fn new(obstacles: &Obstacles, goals: &[WorldPosition]) -> DijkstraMap {
+        DijkstraMap::from_seeds(obstacles, goals.iter().map(|goal| (*goal, 0)))
+    }
+
+    fn from_seeds(
+        obstacles: &Obstacles,
+        seeds: impl Iterator<Item = (WorldPosition, i32)>,
+    ) -> DijkstraMap {
+        let mut map = DijkstraMap {
+            width: obstacles.width,
+            distances: vec![None; obstacles.blocked.len()],
+        };
+        let mut open = BinaryHeap::new();
+        for (world_position, distance) in seeds {
+            if let Some(index) = obstacles.index(world_position) {
+                map.distances[index] = Some(distance);
+                open.push(Reverse((distance, world_position)));
+            }
+        }
+
+        while let Some(Reverse((distance, current))) = open.pop() {
+            if map.distance(current).is_some_and(|d| d < distance) {
+                continue;
+            }
+            for (_, neighbour) in obstacles.neighbours(current) {
+                let index = obstacles.index(neighbour).unwrap();
+                if map.distances[index].is_none_or(|d| distance + 1 < d) {
+                    map.distances[index] = Some(distance + 1);
+                    open.push(Reverse((distance + 1, neighbour)));
+                }
+            }
+        }
+        map
+    }
+
+    /// Map leading away from the goals of this one, routing around them when cornered.
+    pub fn flee(&self, obstacles: &Obstacles) -> DijkstraMap {
+        let (numerator, denominator) = FLEE_COEFFICIENT;
+        let seeds = self
+            .distances
+            .iter()
+            .enumerate()
+            .filter_map(|(index, distance)| {
+                distance.map(|distance| {
+                    let world_position = WorldPosition {
+                        x: index as u64 % self.width,
+                        y: index as u64 / self.width,
+                    };
+                    (world_position, distance * numerator / denominator)
+                })
+            });
+        DijkstraMap::from_seeds(obstacles, seeds)
+    }
+
+    /// `None` for cells which can't reach any goal.
+    pub fn distance(&self, world_position: WorldPosition) -> Option<i32> {
+        if world_position.x >= self.width {
+            return None;
+        }
+        let index = (world_position.y * self.width + world_position.x) as usize;
+        self.distances.get(index).copied().flatten()
+    }
+
+    /// Steps getting closer to a goal, best first.
+    pub fn downhill(
+        &self,
+        obstacles: &Obstacles,
+        world_position: WorldPosition,
+    ) -> Vec<(Direction, WorldPosition)> {
+        let current = self.distance(world_position);
+        let mut steps: Vec<(Direction, WorldPosition, i32)> = obstacles
+            .neighbours(world_position)
+            .filter_map(|(direction, neighbour)| {
+                self.distance(neighbour)
+                    .filter(|distance| current.is_none_or(|current| *distance < current))
+                    .map(|distance| (direction, neighbour, distance))
+            })
+            .collect();
+        steps.sort_by_key(|(_, _, distance)| *distance);
+        steps
+            .into_iter()
+            .map(|(direction, neighbour, _)| (direction, neighbour))
+            .collect()
+    }
+}
+
+/// Pathfinding data for the current level, shared by every creature.
+///
+/// Obstacles only change with the level, so they are kept until the level changes; maps are
+/// recomputed from them when their goal moves.
+#[derive(Default)]
+pub struct Navigation {
+    level: Option<Entity>,
+    obstacles: Option<Obstacles>,
+    to_player: Option<(WorldPosition, DijkstraMap)>,
+    flee: Option<(WorldPosition, DijkstraMap)>,
+}
+
+impl Navigation {
+    pub fn is_current(&self, level: Entity) -> bool {
+        self.level == Some(level)
+    }
+
+    pub fn reset(&mut self, level: Entity, obstacles: Obstacles) {
+        *self = Navigation {
+            level: level.into(),
+            obstacles: obstacles.into(),
+            to_player: None,
+            flee: None,
+        };
+    }
+
+    pub fn obstacles(&self) -> Option<&Obstacles> {
+        self.obstacles.as_ref()
+    }
+
+    pub fn to_player(&mut self, player_position: WorldPosition) -> Option<&DijkstraMap> {
+        let obstacles = self.obstacles.as_ref()?;
+        if self.to_player.as_ref().map(|(goal, _)| *goal) != Some(player_position) {
+            let map = DijkstraMap::new(obstacles, &[player_position]);
+            self.to_player = Some((player_position, map));
+        }
+        self.to_player.as_ref().map(|(_, map)| map)
+    }
+
+    pub fn flee(&mut self, player_position: WorldPosition) -> Option<&DijkstraMap> {
+        if self.flee.as_ref().map(|(goal, _)| *goal) != Some(player_position) {
+            self.to_player(player_position);
+            let map = match (&self.obstacles, &self.to_player) {
+                (Some(obstacles), Some((_, to_player))) => to_player.flee(obstacles),
+                _ => return None,
+            };
+            self.flee = Some((player_position, map));
+        }
+        self.flee.as_ref().map(|(_, map)| map)
+    }
+
+    /// Steps from `world_position` getting closer to the player, best first.
+    pub fn steps_towards_player(
+        &mut self,
+        player_position: WorldPosition,
+        world_position: WorldPosition,
+    ) -> Vec<(Direction, WorldPosition)> {
+        self.to_player(player_position);
+        match (&self.obstacles, &self.to_player) {
+            (Some(obstacles), Some((_, map))) => map.downhill(obstacles, world_position),
+            _ => vec![],
+        }
+    }
+
+    /// Steps from `world_position` getting away from the player, best first.
+    pub fn steps_away_from_player(
+        &mut self,
+        player_position: WorldPosition,
+        world_position: WorldPosition,
+    ) -> Vec<(Direction, WorldPosition)> {
+        self.flee(player_position);
+        match (&self.obstacles, &self.flee) {
+            (Some(obstacles), Some((_, map))) => map.downhill(obstacles, world_position),
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::world::WorldPosition;
+
+    use super::{a_star, DijkstraMap, Obstacles};
+
+    fn position(x: u64, y: u64) -> WorldPosition {
+        WorldPosition { x, y }
+    }
+
+    /// A vertical wall at x = 2, with a gap at the bottom.
+    fn wall_with_gap() -> Obstacles {
+        let mut obstacles = Obstacles::new(5, 5);
+        for y in 0..4 {
+            obstacles.block(position(2, y));
+        }
+        obstacles
+    }
+
+    #[test]
+    fn a_star_goes_around_walls() {
+        let obstacles = wall_with_gap();
+        let path = a_star(&obstacles, position(0, 0), position(4, 0)).unwrap();
+
+        assert_eq!(path.len(), 12);
+        assert_eq!(path.last(), Some(&position(4, 0)));
+        assert!(path.contains(&position(2, 4)));
+        assert!(path.iter().all(|step| !obstacles.is_blocked(*step)));
+    }
+
+    #[test]
+    fn a_star_gives_up_on_enclosed_goals() {
+        let mut obstacles = wall_with_gap();
+        obstacles.block(position(2, 4));
+
+        assert!(a_star(&obstacles, position(0, 0), position(4, 0)).is_none());
+    }
+
+    #[test]
+    fn dijkstra_map_leads_to_the_goal() {
+        let obstacles = wall_with_gap();
+        let map = DijkstraMap::new(&obstacles, &[position(4, 0)]);
+
+        assert_eq!(map.distance(position(4, 0)), Some(0));
+        assert_eq!(map.distance(position(0, 0)), Some(12));
+        assert_eq!(map.distance(position(2, 0)), None);
+
+        let mut current = position(0, 0);
+        for _ in 0..12 {
+            current = map.downhill(&obstacles, current)[0].1;
+        }
+        assert_eq!(current, position(4, 0));
+    }
+
+    #[test]
+    fn flee_map_leads_away_from_the_goal() {
+        let obstacles = Obstacles::new(10, 1);
+        let map = DijkstraMap::new(&obstacles, &[position(3, 0)]);
+        let flee = map.flee(&obstacles);
+
+        let (_, step) = flee.downhill(&obstacles, position(4, 0))[0];
+        assert_eq!(step, position(5, 0));
+    }
+}
@@ -0,0 +1,135 @@
+use super::{
+    common::{CanvasPosition, CanvasSize},
+    world::{WorldPosition, CELL_SIZE},
+};
+
+/// The part of the level shown on the canvas, kept on the player by `Framing`.
+///
+/// The size of the view comes from the canvas, while the level can be any size. Positions are
+/// translated both ways between the level and the canvas here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Camera {
+    /// The cell in the top left corner of the canvas.
+    pub origin: WorldPosition,
+    /// How many cells fit across the canvas.
+    pub width: u64,
+    /// How many cells fit down the canvas.
+    pub height: u64,
+}
+
+impl Default for Camera {
+    /// The view of the 1500×1500 canvas the game is played on.
+    fn default() -> Self {
+        Camera::new(30, 30)
+    }
+}
+
+impl Camera {
+    pub fn new(width: u64, height: u64) -> Camera {
+        Camera {
+            origin: WorldPosition { x: 0, y: 0 },
+            width,
+            height,
+        }
+    }
+
+    pub fn from_canvas_size(canvas_size: CanvasSize) -> Camera {
+        Camera::new(
+            (canvas_size.width / CELL_SIZE) as u64,
+            (canvas_size.height / CELL_SIZE) as u64,
+        )
+    }
+
+    /// Centres the view on `target`, without showing anything past the edges of a level of
+    /// `level_width` by `level_height` cells. Levels smaller than the view stay in the corner.
+    pub fn follow(&mut self, target: WorldPosition, level_width: u64, level_height: u64) {
+        let along = |target: u64, view: u64, level: u64| {
+            target
+                .saturating_sub(view / 2)
+                .min(level.saturating_sub(view))
+        };
+        self.origin = WorldPosition {
+            x: along(target.x, self.width, level_width),
+            y: along(target.y, self.height, level_height),
+        };
+    }
+
+    pub fn contains(&self, world_position: WorldPosition) -> bool {
+        (self.origin.x..self.origin.x + self.width).contains(&world_position.x)
+            && (self.origin.y..self.origin.y + self.height).contains(&world_position.y)
+    }
+
+    /// The top left corner of the cell on the canvas, if it is in view.
+    pub fn to_canvas(&self, world_position: WorldPosition) -> Option<CanvasPosition> {
+        if !self.contains(world_position) {
+            return None;
+        }
+        Some(CanvasPosition {
+            x: CELL_SIZE * (world_position.x - self.origin.x) as f64,
+            y: CELL_SIZE * (world_position.y - self.origin.y) as f64,
+        })
+    }
+
+    /// The cell under that point of the canvas, if the point is on the canvas.
+    pub fn to_world(&self, canvas_position: CanvasPosition) -> Option<WorldPosition> {
+        if canvas_position.x < 0.0 || canvas_position.y < 0.0 {
+            return None;
+        }
+        let world_position = WorldPosition {
+            x: self.origin.x + (canvas_position.x / CELL_SIZE) as u64,
+            y: self.origin.y + (canvas_position.y / CELL_SIZE) as u64,
+        };
+        self.contains(world_position).then_some(world_position)
+    }
+
+    /// Every cell in view, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = WorldPosition> {
+        let Camera {
+            origin,
+            width,
+            height,
+        } = *self;
+        (origin.y..origin.y + height)
+            .flat_map(move |y| (origin.x..origin.x + width).map(move |x| WorldPosition { x, y }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{common::CanvasPosition, world::WorldPosition};
+
+    use super::Camera;
+
+    #[test]
+    fn the_camera_stops_at_the_edges_of_the_level() {
+        let mut camera = Camera::new(30, 20);
+
+        camera.follow(WorldPosition { x: 100, y: 100 }, 200, 200);
+        assert_eq!(camera.origin, WorldPosition { x: 85, y: 90 });
+
+        camera.follow(WorldPosition { x: 3, y: 195 }, 200, 200);
+        assert_eq!(camera.origin, WorldPosition { x: 0, y: 180 });
+
+        camera.follow(WorldPosition { x: 8, y: 8 }, 10, 10);
+        assert_eq!(camera.origin, WorldPosition { x: 0, y: 0 });
+    }
+
+    #[test]
+    fn positions_translate_both_ways() {
+        let mut camera = Camera::new(30, 30);
+        camera.follow(WorldPosition { x: 50, y: 60 }, 200, 200);
+        let player = WorldPosition { x: 50, y: 60 };
+
+        let corner = camera.to_canvas(player).unwrap();
+        assert_eq!((corner.x, corner.y), (750.0, 750.0));
+        let middle = CanvasPosition {
+            x: corner.x + 25.0,
+            y: corner.y + 25.0,
+        };
+        assert_eq!(camera.to_world(middle), Some(player));
+
+        assert!(camera.to_canvas(WorldPosition { x: 10, y: 60 }).is_none());
+        assert_eq!(camera.to_world(CanvasPosition { x: 1600.0, y: 0.0 }), None);
+        assert_eq!(camera.positions().count(), 900);
+    }
+}
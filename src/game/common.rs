@@ -1,10 +1,12 @@
+use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Copy)]
 pub struct CanvasSize {
     pub width: f64,
     pub height: f64,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct CanvasPosition {
     pub x: f64,
     pub y: f64,
@@ -23,7 +25,7 @@ impl CanvasPosition {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     r: u8,
     g: u8,
@@ -84,6 +86,25 @@ impl Color {
         }
     }
 
+    /// Each channel multiplied by its factor, the factors being kept between 0 and 1.
+    pub fn scaled(self, r: f64, g: f64, b: f64) -> Color {
+        let scale = |channel: u8, factor: f64| (channel as f64 * factor.clamp(0.0, 1.0)) as u8;
+        Color {
+            r: scale(self.r, r),
+            g: scale(self.g, g),
+            b: scale(self.b, b),
+        }
+    }
+
+    /// The channels between 0 and 1.
+    pub fn fractions(self) -> (f64, f64, f64) {
+        (
+            self.r as f64 / 255.0,
+            self.g as f64 / 255.0,
+            self.b as f64 / 255.0,
+        )
+    }
+
     pub fn tinted(self) -> Color {
         Color {
             r: self.r + (255 - self.r) / 2,
@@ -93,7 +114,7 @@ impl Color {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum UIEvent {
     Right,
     Left,
@@ -102,4 +123,8 @@ pub enum UIEvent {
     MouseOver(CanvasPosition),
     MousePress(CanvasPosition),
     MouseLeave,
+    /// Item commands refer to the item by its place in the inventory.
+    UseItem(usize),
+    DropItem(usize),
+    EquipItem(usize),
 }
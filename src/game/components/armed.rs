@@ -1,8 +1,28 @@
+use serde::{Deserialize, Serialize};
 use specs::{Component, Entity, HashMapStorage};
 
+use super::status_effects::StatusEffect;
+
+/// What an attack is made of, which decides how much of it the target resists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Fire,
+    Cold,
+    Poison,
+}
+
 #[derive(Default)]
 pub struct Armed {
     pub damage: u32,
+    pub damage_type: DamageType,
+    /// Raises the chance to hit, against the target's `Damageable::defense`.
+    pub accuracy: u32,
+    /// Chance of a hit dealing double damage.
+    pub critical_chance: f64,
+    /// Put on the targets the attack hits.
+    pub inflicts: Option<StatusEffect>,
     pub targetting: Option<Entity>,
 }
 
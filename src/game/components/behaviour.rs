@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, HashMapStorage};
+
+use crate::game::world::WorldPosition;
+
+/// What an AI controlled creature is currently up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AIState {
+    /// Stays put until something shows up.
+    Idle,
+    /// Roams around at random.
+    Wandering,
+    /// Chases the player, who is in sight.
+    Hunting { last_known_position: WorldPosition },
+    /// Heads to where the player was last seen.
+    Searching { last_known_position: WorldPosition },
+    /// Runs away from the player, who is in sight.
+    Fleeing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Behaviour {
+    pub state: AIState,
+    /// Below this percentage of its maximum health, the creature runs away instead of fighting.
+    pub flee_below_percent: u32,
+}
+
+impl Default for Behaviour {
+    fn default() -> Self {
+        Behaviour {
+            state: AIState::Idle,
+            flee_below_percent: 25,
+        }
+    }
+}
+
+impl Component for Behaviour {
+    type Storage = HashMapStorage<Self>;
+}
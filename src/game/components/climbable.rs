@@ -1,8 +1,13 @@
-use specs::{Component, NullStorage};
+use serde::{Deserialize, Serialize};
+use specs::{Component, HashMapStorage};
 
-#[derive(Default)]
-pub struct Climbable;
+/// Stairs, leading to the level above or below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Climbable {
+    Up,
+    Down,
+}
 
 impl Component for Climbable {
-    type Storage = NullStorage<Self>;
+    type Storage = HashMapStorage<Self>;
 }
@@ -1,9 +1,52 @@
+use serde::{Deserialize, Serialize};
 use specs::{Component, HashMapStorage};
 
-#[derive(Default)]
+use super::armed::DamageType;
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Damageable {
     pub health: u32,
     pub max_health: u32,
+    /// Lowers the chance of attacks to hit, against their `Armed::accuracy`.
+    #[serde(default)]
+    pub defense: u32,
+    /// Taken off the physical damage of every hit.
+    #[serde(default)]
+    pub armor: u32,
+    /// Damage types of which only half gets through.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resistances: Vec<DamageType>,
+    /// Damage types dealing twice as much.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vulnerabilities: Vec<DamageType>,
+}
+
+impl Damageable {
+    pub fn new(health: u32) -> Damageable {
+        Damageable {
+            health,
+            max_health: health,
+            ..Default::default()
+        }
+    }
+
+    /// How much of a hit gets through, with `extra_armor` worn on top of the entity's own.
+    ///
+    /// Resistances and vulnerabilities apply first, then armour is taken off physical damage. A
+    /// hit always deals at least 1 damage.
+    pub fn damage_taken(&self, damage: u32, damage_type: DamageType, extra_armor: u32) -> u32 {
+        let mut damage = damage;
+        if self.resistances.contains(&damage_type) {
+            damage /= 2;
+        }
+        if self.vulnerabilities.contains(&damage_type) {
+            damage *= 2;
+        }
+        if damage_type == DamageType::Physical {
+            damage = damage.saturating_sub(self.armor + extra_armor);
+        }
+        damage.max(1)
+    }
 }
 
 impl Component for Damageable {
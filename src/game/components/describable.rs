@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use specs::{Component, VecStorage};
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Describable {
     pub description: String,
 }
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, HashMapStorage};
+
+/// Where an item is worn, only one item per slot being equipped at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Weapon,
+    Body,
+}
+
+/// An item which does something for its owner while equipped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+    /// Added to the damage of the owner's attacks.
+    #[serde(default)]
+    pub damage: u32,
+    /// Added to the owner's armour.
+    #[serde(default)]
+    pub armor: u32,
+}
+
+impl Component for Equippable {
+    type Storage = HashMapStorage<Self>;
+}
@@ -0,0 +1,9 @@
+use specs::{Component, NullStorage};
+
+/// An `Equippable` item its owner is using.
+#[derive(Default)]
+pub struct Equipped;
+
+impl Component for Equipped {
+    type Storage = NullStorage<Self>;
+}
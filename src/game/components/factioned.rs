@@ -1,12 +1,13 @@
+use serde::{Deserialize, Serialize};
 use specs::{Component, VecStorage};
 
-#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
 pub enum Faction {
     Player,
     Enemy,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Factioned {
     pub faction: Faction,
 }
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, VecStorage};
+
+/// Energy an entity needs before it can act.
+pub const ACTION_THRESHOLD: i32 = 100;
+
+/// Speed at which an entity acts once per turn.
+pub const NORMAL_SPEED: u32 = 100;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    Move,
+    Attack,
+    PickUp,
+    Climb,
+    UseItem,
+    DropItem,
+    EquipItem,
+    Wait,
+}
+
+impl Action {
+    pub fn cost(&self) -> i32 {
+        match self {
+            Action::Move => 100,
+            Action::Attack => 100,
+            Action::PickUp => 50,
+            Action::Climb => 100,
+            Action::UseItem => 100,
+            Action::DropItem => 50,
+            Action::EquipItem => 100,
+            Action::Wait => 100,
+        }
+    }
+}
+
+/// Entities gain `speed` energy every turn and spend it on actions, so faster entities act more
+/// than once per turn and slower ones skip turns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Initiative {
+    pub speed: u32,
+    /// Can go below zero when an action costs more than what was available.
+    pub energy: i32,
+}
+
+impl Initiative {
+    pub fn new(speed: u32) -> Initiative {
+        Initiative { speed, energy: 0 }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.energy >= ACTION_THRESHOLD
+    }
+
+    pub fn spend(&mut self, action: Action) {
+        self.energy -= action.cost();
+    }
+}
+
+impl Component for Initiative {
+    type Storage = VecStorage<Self>;
+}
@@ -1,7 +1,66 @@
-use specs::{Component, Entity, HashMapStorage};
+use std::ops::Deref;
 
-#[derive(Default)]
-pub struct Inventoried { }
+use serde::{Deserialize, Serialize};
+use specs::{
+    prelude::*,
+    storage::{MaskedStorage, Storage},
+    Component, HashMapStorage,
+};
+
+use super::{parent::Parent, pickupable::Pickupable};
+
+/// How many different items an entity carries when its template doesn't say.
+pub const DEFAULT_CAPACITY: u32 = 20;
+
+/// What to do with an item, picked by its place in the inventory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemCommand {
+    Use(usize),
+    Drop(usize),
+    Equip(usize),
+}
+
+/// Carries the pickupable entities parented to it. A stack only takes one place.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Inventoried {
+    pub capacity: u32,
+    /// Carried out by `Inventory`, once the entity gets to act.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<ItemCommand>,
+}
+
+impl Default for Inventoried {
+    fn default() -> Self {
+        Inventoried::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Inventoried {
+    pub fn new(capacity: u32) -> Inventoried {
+        Inventoried {
+            capacity,
+            command: None,
+        }
+    }
+
+    /// The items carried by `owner`, in the order the inventory lists them.
+    pub fn items<P, I>(
+        owner: Entity,
+        entities: &Entities,
+        parent: &Storage<Parent, P>,
+        pickupable: &Storage<Pickupable, I>,
+    ) -> Vec<Entity>
+    where
+        P: Deref<Target = MaskedStorage<Parent>>,
+        I: Deref<Target = MaskedStorage<Pickupable>>,
+    {
+        (entities, parent, pickupable)
+            .join()
+            .filter(|(_, parent, _)| parent.entity == owner)
+            .map(|(item, _, _)| item)
+            .collect()
+    }
+}
 
 impl Component for Inventoried {
     type Storage = HashMapStorage<Self>;
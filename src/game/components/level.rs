@@ -1,8 +1,46 @@
-use specs::{Component, Entity, HashMapStorage};
+use serde::{Deserialize, Serialize};
+use specs::{Component, HashMapStorage};
 
-#[derive(Default)]
+use crate::game::{algorithms::generation::GeneratorKind, tile_map::TileMap};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Level {
     pub generated: bool,
+    /// How far down the dungeon the level is, starting at 1 for the first floor.
+    pub depth: u32,
+    /// Picked at random when the level is generated, unless set beforehand.
+    pub generator: Option<GeneratorKind>,
+    /// Whether the player is on this level. The entities of the other levels are stowed.
+    pub active: bool,
+    /// The terrain of the level while the player is elsewhere, see `TileMap`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile_map: Option<TileMap>,
+    /// Light reaching every cell of the level, 1 being as bright as day.
+    pub ambient_light: f64,
+}
+
+impl Level {
+    pub fn new(depth: u32) -> Level {
+        Level {
+            generated: false,
+            depth,
+            generator: None,
+            active: true,
+            tile_map: None,
+            ambient_light: 1.0,
+        }
+    }
+
+    /// The ambient light of generated levels, darker the deeper they are.
+    pub fn ambient_light_at(depth: u32) -> f64 {
+        (0.65 - 0.15 * depth as f64).max(0.0)
+    }
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level::new(1)
+    }
 }
 
 impl Component for Level {
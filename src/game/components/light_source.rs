@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, HashMapStorage};
+
+use crate::game::common::Color;
+
+/// Lights up the cells around the entity, such as a torch carried by the player or a brazier.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightSource {
+    /// How many cells away the light reaches, fading out along the way.
+    pub radius: u64,
+    pub color: Color,
+    /// How bright the light is right next to the entity, 1 being enough to see clearly.
+    pub intensity: f64,
+}
+
+impl Component for LightSource {
+    type Storage = HashMapStorage<Self>;
+}
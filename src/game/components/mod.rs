@@ -1,17 +1,27 @@
 pub mod ai_controlled;
 pub mod armed;
+pub mod behaviour;
 pub mod climbable;
 pub mod collidable;
 pub mod damageable;
 pub mod describable;
+pub mod equippable;
+pub mod equipped;
 pub mod factioned;
+pub mod initiative;
 pub mod inventoried;
 pub mod level;
+pub mod light_source;
 pub mod movable;
 pub mod opaque;
 pub mod parent;
 pub mod pickupable;
 pub mod player_controlled;
+pub mod regenerating;
 pub mod rendered;
 pub mod sighted;
+pub mod stackable;
+pub mod status_effects;
+pub mod stowed;
+pub mod usable;
 pub mod world_position;
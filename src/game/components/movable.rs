@@ -1,10 +1,11 @@
 use std::f64::consts::PI;
 
+use serde::{Deserialize, Serialize};
 use specs::{Component, VecStorage};
 
-use crate::game::random::random_in_vec;
+use crate::game::random::RandomGenerator;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Direction {
     Right,
     Left,
@@ -13,14 +14,14 @@ pub enum Direction {
 }
 
 impl Direction {
-    pub fn random() -> Direction {
+    pub fn random(rng: &mut RandomGenerator) -> Direction {
         let directions = vec![
             Direction::Right,
             Direction::Left,
             Direction::Up,
             Direction::Down,
         ];
-        random_in_vec(&directions).unwrap().clone()
+        *rng.random_in_vec(&directions).unwrap()
     }
 
     pub fn from_radians(rads: f64) -> Direction {
@@ -36,9 +37,12 @@ impl Direction {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Movable {
     pub unprocessed_move: Option<Direction>,
+    /// Set by `Movement` once the entity stepped onto another cell, until `Looting` saw it.
+    #[serde(skip)]
+    pub moved: bool,
 }
 
 impl Component for Movable {
@@ -1,8 +1,9 @@
+use serde::{Deserialize, Serialize};
 use specs::{Component, VecStorage};
 
 use crate::game::common::UIEvent;
 
-#[derive(Default)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct PlayerControlled {
     pub unprocessed_action: Option<UIEvent>,
 }
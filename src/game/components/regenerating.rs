@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, HashMapStorage};
+
+/// Heals a point of health every `turns_per_point` turns, see `Recovery`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Regenerating {
+    pub turns_per_point: u32,
+    /// Turns rested towards the next point.
+    pub turns: u32,
+    /// The world tick the entity last rested at, none until `Recovery` first sees it.
+    pub ticked_at: Option<u64>,
+}
+
+impl Regenerating {
+    pub fn new(turns_per_point: u32) -> Regenerating {
+        Regenerating {
+            turns_per_point,
+            turns: 0,
+            ticked_at: None,
+        }
+    }
+}
+
+impl Component for Regenerating {
+    type Storage = HashMapStorage<Self>;
+}
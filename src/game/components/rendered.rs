@@ -1,8 +1,9 @@
+use serde::{Deserialize, Serialize};
 use specs::{Component, VecStorage};
 
 use crate::game::common::Color;
 
-#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Serialize, Deserialize)]
 pub enum ZLayer {
     Ground,
     Item,
@@ -10,7 +11,7 @@ pub enum ZLayer {
     Saturating,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Render {
     pub glyph: Option<char>,
     pub foreground_color: Color,
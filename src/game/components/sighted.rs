@@ -1,9 +1,42 @@
+use std::collections::HashSet;
+
 use specs::{BitSet, Component, HashMapStorage};
 
-#[derive(Default)]
+use crate::game::world::WorldPosition;
+
+pub const DEFAULT_SIGHT_RADIUS: u64 = 20;
+
 pub struct Sighted {
+    /// How many cells away the entity sees.
+    pub radius: u64,
     pub seen: BitSet,
     pub seen_recently: BitSet,
+    /// The cells in view from `viewpoint`, kept until the entity or the terrain moves.
+    pub visible: HashSet<WorldPosition>,
+    pub viewpoint: Option<WorldPosition>,
+}
+
+impl Sighted {
+    pub fn new(radius: u64) -> Sighted {
+        Sighted {
+            radius,
+            seen: BitSet::new(),
+            seen_recently: BitSet::new(),
+            visible: HashSet::new(),
+            viewpoint: None,
+        }
+    }
+
+    /// Makes `Perspective` work out the field of view again.
+    pub fn invalidate(&mut self) {
+        self.viewpoint = None;
+    }
+}
+
+impl Default for Sighted {
+    fn default() -> Self {
+        Sighted::new(DEFAULT_SIGHT_RADIUS)
+    }
 }
 
 impl Component for Sighted {
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, HashMapStorage};
+
+/// Items of the same kind share a single place in an inventory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stackable {
+    /// The template the item was built from, items only stack with their own kind.
+    pub kind: String,
+    pub count: u32,
+}
+
+impl Stackable {
+    pub fn new(kind: &str) -> Stackable {
+        Stackable {
+            kind: kind.to_owned(),
+            count: 1,
+        }
+    }
+}
+
+impl Component for Stackable {
+    type Storage = HashMapStorage<Self>;
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, Entity, HashMapStorage, WriteStorage};
+
+/// Speed is multiplied by this while hasted.
+pub const HASTE_SPEED_MULTIPLIER: u32 = 2;
+
+/// How many cells away blinded entities still see.
+pub const BLIND_SIGHT_RADIUS: u64 = 1;
+
+/// Chance of a confused entity stumbling in a random direction instead of where it meant to go.
+pub const CONFUSED_STUMBLE_CHANCE: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StatusEffectKind {
+    /// Takes `strength` poison damage every turn.
+    Poison,
+    /// Heals `strength` health every turn.
+    Regeneration,
+    Haste,
+    Blindness,
+    Confusion,
+}
+
+impl StatusEffectKind {
+    /// How an entity under the effect is described.
+    pub fn adjective(&self) -> &'static str {
+        match self {
+            StatusEffectKind::Poison => "poisoned",
+            StatusEffectKind::Regeneration => "regenerating",
+            StatusEffectKind::Haste => "hasted",
+            StatusEffectKind::Blindness => "blind",
+            StatusEffectKind::Confusion => "confused",
+        }
+    }
+
+    pub fn is_harmful(&self) -> bool {
+        !matches!(
+            self,
+            StatusEffectKind::Regeneration | StatusEffectKind::Haste
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    /// Turns left before the effect wears off.
+    pub turns: u32,
+    /// Only used by the effects acting every turn.
+    #[serde(default)]
+    pub strength: u32,
+}
+
+/// The temporary states an entity is in, worn off one turn at a time by `Effects`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+    /// The world tick the effects were last worn off at, none until `Effects` first sees them.
+    pub ticked_at: Option<u64>,
+}
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusEffectKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// Adds the effect, returning whether the entity wasn't already under it. An effect the
+    /// entity is already under lasts as long and is as strong as the longest and strongest one.
+    pub fn add(&mut self, effect: StatusEffect) -> bool {
+        match self
+            .effects
+            .iter_mut()
+            .find(|existing| existing.kind == effect.kind)
+        {
+            Some(existing) => {
+                existing.turns = existing.turns.max(effect.turns);
+                existing.strength = existing.strength.max(effect.strength);
+                false
+            }
+            None => {
+                self.effects.push(effect);
+                true
+            }
+        }
+    }
+
+    /// Adds the effect to the entity, returning whether it wasn't already under it.
+    pub fn afflict(
+        status_effects: &mut WriteStorage<StatusEffects>,
+        entity: Entity,
+        effect: StatusEffect,
+    ) -> bool {
+        match status_effects.entry(entity) {
+            Ok(entry) => entry.or_insert_with(StatusEffects::default).add(effect),
+            Err(_) => false,
+        }
+    }
+}
+
+impl Component for StatusEffects {
+    type Storage = HashMapStorage<Self>;
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, VecStorage};
+
+use crate::game::world::WorldPosition;
+
+/// Where an entity of a level the player left was, kept aside until the player comes back.
+///
+/// Stowed entities have no `WorldPosition`, so the systems acting on the level the player is on
+/// leave them alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stowed {
+    pub world_position: WorldPosition,
+}
+
+impl Component for Stowed {
+    type Storage = VecStorage<Self>;
+}
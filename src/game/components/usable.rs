@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, HashMapStorage};
+
+use super::status_effects::StatusEffect;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemEffect {
+    /// The whole level the user is on becomes explored.
+    RevealMap,
+    /// Gives the user back that much health, up to their `max_health`.
+    Heal(u32),
+    /// The user comes under the effect.
+    Afflict(StatusEffect),
+}
+
+/// An item used up for its effect, one at a time when stacked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Usable {
+    pub effect: ItemEffect,
+}
+
+impl Component for Usable {
+    type Storage = HashMapStorage<Self>;
+}
@@ -1,13 +1,14 @@
 use std::cmp;
 
-use specs::{Component, VecStorage};
+use specs::{Component, FlaggedStorage, VecStorage};
 
 use crate::game::world::WorldPosition;
 
 use super::movable::Direction;
 
+/// Flagged so that the `SpatialIndex` can follow positions as they change.
 impl Component for WorldPosition {
-    type Storage = VecStorage<Self>;
+    type Storage = FlaggedStorage<Self, VecStorage<Self>>;
 }
 
 impl WorldPosition {
@@ -0,0 +1,318 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    algorithms::generation::vaults::Vault,
+    components::{
+        armed::DamageType, climbable::Climbable, equippable::Equippable, factioned::Faction,
+        light_source::LightSource, rendered::Render, status_effects::StatusEffect, usable::Usable,
+    },
+    random::RandomGenerator,
+    tile_map::Tile,
+};
+
+/// Entity templates, spawn tables and vaults shipped with the game, see `content/entities.json`.
+pub const BUNDLED_CONTENT: &str = include_str!("../../content/entities.json");
+
+#[derive(Debug)]
+pub enum ContentError {
+    Malformed(serde_json::Error),
+    UnknownTemplate(String),
+    MalformedVault { vault: String, problem: String },
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentError::Malformed(error) => write!(f, "Malformed content: {}", error),
+            ContentError::UnknownTemplate(name) => write!(f, "Unknown template {}", name),
+            ContentError::MalformedVault { vault, problem } => {
+                write!(f, "Malformed vault {}: {}", vault, problem)
+            }
+        }
+    }
+}
+
+/// The components an entity is built with. Anything left out is not added to the entity.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct EntityTemplate {
+    pub description: Option<String>,
+    pub render: Option<Render>,
+    pub faction: Option<Faction>,
+    pub health: Option<u32>,
+    pub defense: u32,
+    pub armor: u32,
+    pub resistances: Vec<DamageType>,
+    pub vulnerabilities: Vec<DamageType>,
+    /// Turns the entity rests to heal a point of health, entities without it don't heal on
+    /// their own.
+    pub regeneration: Option<u32>,
+    /// Entities without damage don't attack.
+    pub damage: Option<u32>,
+    pub damage_type: DamageType,
+    pub accuracy: u32,
+    pub critical_chance: f64,
+    /// Put on the targets of the entity's hits.
+    pub inflicts: Option<StatusEffect>,
+    /// Entities without a speed never get a turn.
+    pub speed: Option<u32>,
+    pub player_controlled: bool,
+    pub ai_controlled: bool,
+    pub movable: bool,
+    /// How many different items the entity carries, entities without it carry nothing.
+    pub inventory_capacity: Option<u32>,
+    pub collidable: bool,
+    pub opaque: bool,
+    pub pickupable: bool,
+    /// Stacks with the other items of the same template.
+    pub stackable: bool,
+    pub usable: Option<Usable>,
+    pub equippable: Option<Equippable>,
+    pub climbable: Option<Climbable>,
+    pub light_source: Option<LightSource>,
+    /// How many cells away the entity sees, entities without it are blind.
+    pub sight_radius: Option<u64>,
+}
+
+/// One of the templates a spawn table can pick from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnEntry {
+    pub template: String,
+    /// How likely the entry is to be picked compared to the others available at that depth.
+    pub weight: u32,
+    pub min_depth: u32,
+    /// The entry keeps being available all the way down when not set.
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+}
+
+impl SpawnEntry {
+    fn is_available_at(&self, depth: u32) -> bool {
+        self.min_depth <= depth && self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+}
+
+/// What gets spawned on a floor, depending on its depth.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTable {
+    /// How many entities are spawned on the first floor.
+    pub count: u32,
+    /// How many more are spawned for every floor below the first one.
+    #[serde(default)]
+    pub count_per_depth: f64,
+    /// Chance for each pick to be made as if the floor was `out_of_depth_floors` deeper.
+    #[serde(default)]
+    pub out_of_depth_chance: f64,
+    #[serde(default)]
+    pub out_of_depth_floors: u32,
+    pub entries: Vec<SpawnEntry>,
+}
+
+impl SpawnTable {
+    pub fn count_at(&self, depth: u32) -> u32 {
+        self.count + (depth.saturating_sub(1) as f64 * self.count_per_depth) as u32
+    }
+
+    /// Picks the template to spawn, or `None` if nothing is available at that depth.
+    pub fn roll(&self, depth: u32, rng: &mut RandomGenerator) -> Option<&str> {
+        let depth = if rng.next_f64() < self.out_of_depth_chance {
+            depth + self.out_of_depth_floors
+        } else {
+            depth
+        };
+        let available: Vec<&SpawnEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.is_available_at(depth) && entry.weight > 0)
+            .collect();
+        let total_weight: u32 = available.iter().map(|entry| entry.weight).sum();
+        if total_weight == 0 {
+            return None;
+        }
+        let mut roll = rng.random_in_range(0, total_weight as u64 - 1) as u32;
+        for entry in available {
+            if roll < entry.weight {
+                return Some(&entry.template);
+            }
+            roll -= entry.weight;
+        }
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Spawns {
+    pub items: SpawnTable,
+    pub monsters: SpawnTable,
+}
+
+/// Every entity template by name, and the spawn tables and vaults picking from them. Vaults can
+/// also pick tiles, by name.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Content {
+    templates: HashMap<String, EntityTemplate>,
+    pub spawns: Spawns,
+    /// Sorted by name, so that vaults are rolled for in the same order on every run.
+    #[serde(default)]
+    pub vaults: BTreeMap<String, Vault>,
+}
+
+impl Content {
+    pub fn from_json(json: &str) -> Result<Content, ContentError> {
+        let content: Content = serde_json::from_str(json).map_err(ContentError::Malformed)?;
+        for (name, vault) in content.vaults.iter() {
+            if let Some(problem) = vault.problem() {
+                return Err(ContentError::MalformedVault {
+                    vault: name.clone(),
+                    problem,
+                });
+            }
+        }
+
+        let tables = [&content.spawns.items, &content.spawns.monsters];
+        let spawned = tables
+            .iter()
+            .flat_map(|table| table.entries.iter())
+            .map(|entry| &entry.template);
+        for name in spawned {
+            if content.template(name).is_none() {
+                return Err(ContentError::UnknownTemplate(name.clone()));
+            }
+        }
+        // Vaults also lay down terrain, which isn't made of entities
+        let stamped = content
+            .vaults
+            .values()
+            .flat_map(|vault| vault.legend.values().flatten());
+        for name in stamped {
+            if content.template(name).is_none() && Tile::from_name(name).is_none() {
+                return Err(ContentError::UnknownTemplate(name.clone()));
+            }
+        }
+        Ok(content)
+    }
+
+    pub fn template(&self, name: &str) -> Option<&EntityTemplate> {
+        self.templates.get(name)
+    }
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Content::from_json(BUNDLED_CONTENT).expect("Bundled content is malformed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Content, ContentError};
+    use crate::game::random::RandomGenerator;
+
+    fn rolls(content: &Content, depth: u32) -> Vec<String> {
+        let mut rng = RandomGenerator::from_seed(1);
+        (0..200)
+            .filter_map(|_| content.spawns.monsters.roll(depth, &mut rng))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn bundled_content_has_the_generated_templates() {
+        let content = Content::default();
+        for name in ["player", "gold", "stairs", "up_stairs"] {
+            assert!(
+                content.template(name).is_some(),
+                "Missing template {}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_components_are_rejected() {
+        let json = r#"{
+            "templates": { "bat": { "description": "Bat", "wings": true } },
+            "spawns": {
+                "items": { "count": 0, "entries": [] },
+                "monsters": { "count": 0, "entries": [] }
+            }
+        }"#;
+        assert!(matches!(
+            Content::from_json(json),
+            Err(ContentError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_templates_are_rejected() {
+        let json = r#"{
+            "templates": {},
+            "spawns": {
+                "items": { "count": 1, "entries": [] },
+                "monsters": {
+                    "count": 1,
+                    "entries": [{ "template": "bat", "weight": 1, "min_depth": 1 }]
+                }
+            }
+        }"#;
+        assert!(matches!(
+            Content::from_json(json),
+            Err(ContentError::UnknownTemplate(name)) if name == "bat"
+        ));
+    }
+
+    #[test]
+    fn vaults_must_match_their_legend() {
+        let json = r#"{
+            "templates": {},
+            "spawns": {
+                "items": { "count": 0, "entries": [] },
+                "monsters": { "count": 0, "entries": [] }
+            },
+            "vaults": {
+                "pit": {
+                    "rows": [".?."],
+                    "legend": { ".": ["floor"] },
+                    "chance": 1.0,
+                    "min_depth": 1
+                }
+            }
+        }"#;
+        assert!(matches!(
+            Content::from_json(json),
+            Err(ContentError::MalformedVault { vault, .. }) if vault == "pit"
+        ));
+    }
+
+    #[test]
+    fn deeper_floors_spawn_tougher_monsters() {
+        let mut content = Content::default();
+        content.spawns.monsters.out_of_depth_chance = 0.0;
+        let health = |name: &String| content.template(name).and_then(|t| t.health).unwrap();
+
+        let shallow = rolls(&content, 1);
+        let deep = rolls(&content, 8);
+        assert!(shallow.iter().all(|name| name == "rat" || name == "goblin"));
+        assert!(!deep.iter().any(|name| name == "rat"));
+        assert!(deep.iter().map(health).sum::<u32>() > shallow.iter().map(health).sum::<u32>() * 2);
+        assert!(content.spawns.monsters.count_at(8) > content.spawns.monsters.count_at(1));
+    }
+
+    #[test]
+    fn out_of_depth_picks_come_from_deeper_floors() {
+        let mut content = Content::default();
+        content.spawns.monsters.out_of_depth_chance = 1.0;
+        assert!(rolls(&content, 3)
+            .iter()
+            .all(|name| name == "orc" || name == "troll"));
+    }
+}
@@ -0,0 +1,74 @@
+use specs::Entity;
+
+use super::{
+    components::{armed::DamageType, climbable::Climbable, status_effects::StatusEffectKind},
+    world::WorldPosition,
+};
+
+/// Something that happened in the game.
+///
+/// Systems publish them on the `EventChannel<GameEvent>` resource, for the systems reacting to
+/// them and for the message log. Entities named in an event are still alive until the end of
+/// the tick it was sent in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    Hit {
+        attacker: Entity,
+        target: Entity,
+        damage: u32,
+        damage_type: DamageType,
+        critical: bool,
+    },
+    Missed {
+        attacker: Entity,
+        target: Entity,
+    },
+    /// The entity ran out of health, `Death` leaves its corpse behind.
+    Died {
+        entity: Entity,
+        killer: Option<Entity>,
+    },
+    PickedUp {
+        entity: Entity,
+        item: Entity,
+    },
+    /// The entity stands on an item it has no room for.
+    InventoryFull {
+        entity: Entity,
+    },
+    /// The entity took the stairs to the level at `depth`.
+    Climbed {
+        entity: Entity,
+        direction: Climbable,
+        depth: u32,
+    },
+    /// The entity took the stairs up from the first level.
+    NoWayOut {
+        entity: Entity,
+    },
+    /// The entity walked into terrain it can't cross.
+    Bumped {
+        entity: Entity,
+        position: WorldPosition,
+    },
+    /// The entity came under an effect it wasn't already under.
+    Afflicted {
+        entity: Entity,
+        kind: StatusEffectKind,
+    },
+    /// An effect the entity was under wore off.
+    Recovered {
+        entity: Entity,
+        kind: StatusEffectKind,
+    },
+    /// The entity got `amount` health back from an item.
+    Healed {
+        entity: Entity,
+        amount: u32,
+    },
+    /// The entity took damage from the poison it is under.
+    Poisoned {
+        entity: Entity,
+        damage: u32,
+    },
+}
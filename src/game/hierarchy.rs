@@ -11,7 +11,7 @@ extern crate shrev;
 ///
 /// ## Usage
 ///
-/// ```rust
+/// ```rust,ignore
 /// # extern crate specs;
 /// # extern crate specs_hierarchy;
 ///
@@ -235,12 +235,14 @@ impl<P> Hierarchy<P> {
             let mut min_index = std::usize::MAX;
             while i < self.sorted.len() {
                 let entity = self.sorted[i];
+                // Children moved to another parent in the same frame stay in the hierarchy
                 let remove = self.scratch_set.contains(&entity)
-                    || self
-                        .current_parent
-                        .get(&entity)
-                        .map(|parent_entity| self.scratch_set.contains(&parent_entity))
-                        .unwrap_or(false);
+                    || (!self.modified.contains(entity.id())
+                        && self
+                            .current_parent
+                            .get(&entity)
+                            .map(|parent_entity| self.scratch_set.contains(&parent_entity))
+                            .unwrap_or(false));
 
                 if remove {
                     if i < min_index {
@@ -360,7 +362,7 @@ impl<P> Hierarchy<P> {
 
                 // fix indexes
                 if parent_index > entity_index {
-                    for i in entity_index..parent_index {
+                    for i in entity_index..=parent_index {
                         self.entities.insert(self.sorted[i].id(), i);
                     }
                 }
@@ -628,6 +630,37 @@ mod tests {
         assert_eq!(0, world.read_resource::<Hierarchy<Parent>>().all().len());
     }
 
+    #[test]
+    fn children_moved_away_from_a_removed_parent_stay() {
+        let mut world = World::new();
+        world.register::<Parent>();
+        let mut system = HierarchySystem::<Parent>::new(&mut world);
+        let mut reader_id = world.write_resource::<Hierarchy<Parent>>().track();
+
+        let e1 = world.create_entity().build();
+        let e2 = world.create_entity().with(Parent { entity: e1 }).build();
+        let e3 = world.create_entity().with(Parent { entity: e2 }).build();
+        let e4 = world.create_entity().with(Parent { entity: e2 }).build();
+        system.run_now(&mut world);
+        delete_removals(&mut world, &mut reader_id);
+        world.maintain();
+
+        world
+            .write_storage::<Parent>()
+            .insert(e3, Parent { entity: e1 })
+            .unwrap();
+        let _ = world.delete_entity(e2);
+        system.run_now(&mut world);
+        delete_removals(&mut world, &mut reader_id);
+        world.maintain();
+
+        assert!(world.is_alive(e3));
+        assert!(!world.is_alive(e4));
+        let hierarchy = world.read_resource::<Hierarchy<Parent>>();
+        assert_eq!(hierarchy.parent(e3), Some(e1));
+        assert_eq!(hierarchy.children(e1), &[e3]);
+    }
+
     #[test]
     fn test_all_children_iter() {
         let mut world = World::new();
@@ -696,4 +729,35 @@ mod tests {
         assert_eq!(hierarchy.all_children(e4).iter().next(), None);
         assert_eq!(hierarchy.all_children(e5).iter().next(), None);
     }
+
+    #[test]
+    fn reparenting_under_a_later_entity() {
+        let mut world = World::new();
+        world.register::<Parent>();
+        let mut system = HierarchySystem::<Parent>::new(&mut world);
+        let e0 = world.create_entity().build();
+
+        let e1 = world.create_entity().with(Parent { entity: e0 }).build();
+
+        let e2 = world.create_entity().with(Parent { entity: e0 }).build();
+
+        let e3 = world.create_entity().with(Parent { entity: e0 }).build();
+
+        let e4 = world.create_entity().with(Parent { entity: e0 }).build();
+
+        system.run_now(&mut world);
+        world.maintain();
+
+        world.write_storage::<Parent>().get_mut(e1).unwrap().entity = e4;
+        system.run_now(&mut world);
+        world.maintain();
+
+        let _ = world.delete_entity(e3);
+        system.run_now(&mut world);
+        world.maintain();
+
+        let hierarchy = world.read_resource::<Hierarchy<Parent>>();
+        assert_eq!(hierarchy.all(), &[e4, e1, e2]);
+        assert_eq!(hierarchy.children(e4), &[e1]);
+    }
 }
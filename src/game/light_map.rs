@@ -0,0 +1,122 @@
+use super::{
+    algorithms::fov::field_of_view, common::Color, components::light_source::LightSource,
+    world::WorldPosition,
+};
+
+/// Cells need at least that much light to be seen from afar.
+pub const MIN_BRIGHTNESS: f64 = 0.1;
+
+/// How dark colours get under the dimmest light, so that lit cells stay readable.
+const MIN_SHADE: f64 = 0.35;
+
+/// Light falling on a cell, per colour channel. 1 is enough to see clearly.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Light {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+impl Light {
+    pub fn white(intensity: f64) -> Light {
+        Light {
+            r: intensity,
+            g: intensity,
+            b: intensity,
+        }
+    }
+
+    pub fn colored(color: Color, intensity: f64) -> Light {
+        let (r, g, b) = color.fractions();
+        Light {
+            r: r * intensity,
+            g: g * intensity,
+            b: b * intensity,
+        }
+    }
+
+    pub fn brightness(&self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// What the colour looks like under this light.
+    pub fn shine_on(&self, color: Color) -> Color {
+        let shade = |channel: f64| channel.clamp(MIN_SHADE, 1.0);
+        color.scaled(shade(self.r), shade(self.g), shade(self.b))
+    }
+
+    fn add(&mut self, other: Light) {
+        self.r += other.r;
+        self.g += other.g;
+        self.b += other.b;
+    }
+}
+
+/// The light on every cell of the level the player is on, on top of the level's ambient light.
+///
+/// Worked out again by `Lighting` whenever the lights or what blocks them changed, so it is never
+/// saved. Cells off the map only get the ambient light.
+#[derive(Clone)]
+pub struct LightMap {
+    width: u64,
+    height: u64,
+    ambient: Light,
+    lights: Vec<Light>,
+}
+
+impl Default for LightMap {
+    /// Fully lit, as when nothing was worked out yet.
+    fn default() -> Self {
+        LightMap::new(0, 0, 1.0)
+    }
+}
+
+impl LightMap {
+    pub fn new(width: u64, height: u64, ambient: f64) -> LightMap {
+        LightMap {
+            width,
+            height,
+            ambient: Light::white(ambient),
+            lights: vec![Light::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn light_at(&self, world_position: WorldPosition) -> Light {
+        let mut light = self.ambient;
+        if let Some(index) = self.index(world_position) {
+            light.add(self.lights[index]);
+        }
+        light
+    }
+
+    pub fn is_lit(&self, world_position: WorldPosition) -> bool {
+        self.light_at(world_position).brightness() >= MIN_BRIGHTNESS
+    }
+
+    /// Lights up the cells the source reaches from `origin`, dimmer the further away they are.
+    pub fn add_source(
+        &mut self,
+        origin: WorldPosition,
+        light_source: &LightSource,
+        is_opaque: impl Fn(WorldPosition) -> bool,
+    ) {
+        let reach = (light_source.radius + 1) as f64;
+        for world_position in field_of_view(origin, light_source.radius, is_opaque) {
+            if let Some(index) = self.index(world_position) {
+                let falloff = 1.0 - origin.distance_from(world_position) / reach;
+                self.lights[index].add(Light::colored(
+                    light_source.color,
+                    light_source.intensity * falloff,
+                ));
+            }
+        }
+    }
+
+    fn index(&self, world_position: WorldPosition) -> Option<usize> {
+        if world_position.x < self.width && world_position.y < self.height {
+            Some((world_position.y * self.width + world_position.x) as usize)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::VecDeque;
+
+/// How many messages the log keeps, older ones are forgotten.
+pub const MAX_MESSAGES: usize = 100;
+
+/// What a message is about, which decides how it stands out in the log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    Info,
+    /// The player fighting.
+    Combat,
+    /// The player getting hurt.
+    Danger,
+    Loot,
+    Travel,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub text: String,
+    pub kind: MessageKind,
+}
+
+/// The latest messages for the player, written by `Messages` from the game events.
+///
+/// It is only there to be read by the frontend, so it is never saved.
+#[derive(Default)]
+pub struct MessageLog {
+    messages: VecDeque<Message>,
+    total: usize,
+}
+
+impl MessageLog {
+    pub fn push(&mut self, text: String, kind: MessageKind) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(Message { text, kind });
+        self.total += 1;
+    }
+
+    /// Oldest first.
+    pub fn messages(&self) -> impl Iterator<Item = &Message> {
+        self.messages.iter()
+    }
+
+    /// How many messages were ever pushed, for readers to tell which ones they haven't seen.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The messages pushed after the `total`-th, oldest first.
+    pub fn since(&self, total: usize) -> impl Iterator<Item = &Message> {
+        let new = self.total.saturating_sub(total).min(self.messages.len());
+        self.messages.iter().skip(self.messages.len() - new)
+    }
+}
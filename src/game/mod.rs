@@ -1,8 +1,22 @@
 pub mod algorithms;
+pub mod camera;
 pub mod common;
 pub mod components;
+pub mod content;
+pub mod events;
 pub mod hierarchy;
+pub mod light_map;
+pub mod message_log;
 pub mod random;
+pub mod replay;
+pub mod simulation;
+pub mod snapshot;
+pub mod spatial_index;
+pub mod spawner;
 pub mod systems;
+#[cfg(test)]
+pub mod test_harness;
+pub mod tile_map;
+#[cfg(feature = "web")]
 pub mod ui;
 pub mod world;
@@ -1,28 +1,138 @@
 use std::cmp;
 
-use js_sys::Math::random;
+use serde::{Deserialize, Serialize};
 
-pub fn random_in_range(x: u64, y: u64) -> u64 {
-    let range = y - x + 1;
-    let random_ratio = random();
-    cmp::min(x + ((range as f64) * random_ratio) as u64, y)
+const LEVEL_GENERATION_STREAM: u64 = 1;
+const AI_STREAM: u64 = 2;
+const COMBAT_STREAM: u64 = 3;
+const STATUS_EFFECTS_STREAM: u64 = 4;
+
+/// Small SplitMix64 generator.
+///
+/// It is seeded explicitly so that any run can be reproduced from its seed, and it does not
+/// depend on the browser so it also works in native builds.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RandomGenerator {
+    state: u64,
 }
 
-pub fn random_index_vec<'a, T>(vector: &'a Vec<T>) -> Option<usize> {
-    if vector.is_empty() {
-        return None;
+impl RandomGenerator {
+    pub fn from_seed(seed: u64) -> RandomGenerator {
+        RandomGenerator { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform ratio in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn random_in_range(&mut self, x: u64, y: u64) -> u64 {
+        let range = y - x + 1;
+        let random_ratio = self.next_f64();
+        cmp::min(x + ((range as f64) * random_ratio) as u64, y)
+    }
+
+    pub fn random_index_vec<T>(&mut self, vector: &[T]) -> Option<usize> {
+        if vector.is_empty() {
+            return None;
+        }
+        let len = vector.len();
+        let i = self.random_in_range(0, (len - 1) as u64);
+        Some(i as usize)
+    }
+
+    pub fn random_in_vec<'a, T>(&mut self, vector: &'a [T]) -> Option<&'a T> {
+        let index = self.random_index_vec(vector);
+        index.and_then(|i| vector.get(i))
+    }
+
+    pub fn random_in_vec_and_remove<T>(&mut self, vector: &mut Vec<T>) -> Option<T> {
+        let index = self.random_index_vec(vector);
+        index.map(|i| vector.remove(i))
     }
-    let len = vector.len();
-    let i = random_in_range(0, (len - 1) as u64);
-    Some(i as usize)
 }
 
-pub fn random_in_vec<'a, T>(vector: &'a Vec<T>) -> Option<&'a T> {
-    let index = random_index_vec(vector);
-    index.and_then(|i| vector.get(i))
+/// Seeded random streams, one per subsystem.
+///
+/// Each subsystem draws from its own stream so that, for instance, the number of AI decisions
+/// taken on a floor never changes how the next floor is generated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Randomness {
+    pub seed: u64,
+    pub level_generation: RandomGenerator,
+    pub ai: RandomGenerator,
+    pub combat: RandomGenerator,
+    pub status_effects: RandomGenerator,
 }
 
-pub fn random_in_vec_and_remove<'a, T>(vector: &'a mut Vec<T>) -> Option<T> {
-    let index = random_index_vec(vector);
-    index.and_then(|i| vector.remove(i).into())
+impl Randomness {
+    pub fn from_seed(seed: u64) -> Randomness {
+        Randomness {
+            seed,
+            level_generation: Randomness::stream(seed, LEVEL_GENERATION_STREAM),
+            ai: Randomness::stream(seed, AI_STREAM),
+            combat: Randomness::stream(seed, COMBAT_STREAM),
+            status_effects: Randomness::stream(seed, STATUS_EFFECTS_STREAM),
+        }
+    }
+
+    fn stream(seed: u64, stream: u64) -> RandomGenerator {
+        let mut mixer = RandomGenerator::from_seed(seed ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03));
+        RandomGenerator::from_seed(mixer.next_u64())
+    }
+}
+
+impl Default for Randomness {
+    fn default() -> Self {
+        Randomness::from_seed(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RandomGenerator, Randomness};
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Randomness::from_seed(12345);
+        let mut b = Randomness::from_seed(12345);
+        for _ in 0..100 {
+            assert_eq!(
+                a.level_generation.random_in_range(0, 29),
+                b.level_generation.random_in_range(0, 29)
+            );
+        }
+    }
+
+    #[test]
+    fn streams_are_independent() {
+        let mut a = Randomness::from_seed(7);
+        let mut b = Randomness::from_seed(7);
+        for _ in 0..50 {
+            a.ai.next_u64();
+        }
+        for _ in 0..100 {
+            assert_eq!(a.level_generation.next_u64(), b.level_generation.next_u64());
+        }
+    }
+
+    #[test]
+    fn range_is_inclusive_and_bounded() {
+        let mut rng = RandomGenerator::from_seed(1);
+        let mut seen = [false; 4];
+        for _ in 0..1000 {
+            let value = rng.random_in_range(2, 5);
+            assert!((2..=5).contains(&value));
+            seen[(value - 2) as usize] = true;
+        }
+        assert!(seen.iter().all(|s| *s));
+    }
 }
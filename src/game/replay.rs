@@ -0,0 +1,308 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    common::UIEvent,
+    simulation::Simulation,
+    snapshot::{Snapshot, SnapshotError, SNAPSHOT_VERSION},
+    world::WorldParameters,
+};
+
+/// Bumped whenever the replay layout changes in a way older replays can't be read with.
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Malformed(serde_json::Error),
+    /// Either the replay's version or the version of the snapshot it starts from.
+    UnsupportedVersion(u32),
+    /// The saved game the replay starts from can't be restored.
+    InvalidStart(SnapshotError),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Malformed(error) => write!(f, "Malformed replay: {}", error),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "Version {} is not supported (expected replay version {} and snapshot version {})",
+                version, REPLAY_VERSION, SNAPSHOT_VERSION
+            ),
+            ReplayError::InvalidStart(error) => write!(f, "Invalid replay start: {}", error),
+        }
+    }
+}
+
+/// Events received during a single dispatch.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayFrame {
+    pub dispatch: u64,
+    pub events: Vec<UIEvent>,
+}
+
+/// Everything needed to reproduce a run: where it started and the events of every dispatch.
+///
+/// Only dispatches which received events are stored; the others are implied by `dispatches`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub world_parameters: WorldParameters,
+    /// Set when the run was resumed from a saved game rather than started from the seed.
+    pub start: Option<Snapshot>,
+    pub dispatches: u64,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Deserialize)]
+struct ReplayHeader {
+    version: u32,
+    start: Option<StartHeader>,
+}
+
+#[derive(Deserialize)]
+struct StartHeader {
+    version: u32,
+}
+
+impl Replay {
+    pub fn new(seed: u64, world_parameters: WorldParameters, start: Option<Snapshot>) -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            world_parameters,
+            start,
+            dispatches: 0,
+            frames: vec![],
+        }
+    }
+
+    pub fn record(&mut self, events: &[UIEvent]) {
+        if !events.is_empty() {
+            self.frames.push(ReplayFrame {
+                dispatch: self.dispatches,
+                events: events.to_vec(),
+            });
+        }
+        self.dispatches += 1;
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Replay, ReplayError> {
+        let header: ReplayHeader = serde_json::from_str(json).map_err(ReplayError::Malformed)?;
+        if header.version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(header.version));
+        }
+        // A resumed run is only reproduced from a saved game this version can restore
+        match header.start {
+            Some(start) if start.version != SNAPSHOT_VERSION => {
+                return Err(ReplayError::UnsupportedVersion(start.version));
+            }
+            _ => {}
+        }
+        let replay: Replay = serde_json::from_str(json).map_err(ReplayError::Malformed)?;
+        if let Some(start) = &replay.start {
+            start.check_entities().map_err(ReplayError::InvalidStart)?;
+        }
+        Ok(replay)
+    }
+
+    /// Plays the whole replay without a frontend.
+    pub fn run(&self) -> Simulation<'static, 'static> {
+        let mut simulation = Simulation::from_replay(self);
+        while simulation.is_replaying() {
+            simulation.tick(vec![]);
+        }
+        simulation
+    }
+}
+
+/// Hands out the recorded events of a replay, one dispatch at a time.
+pub struct Replayer {
+    replay: Replay,
+    dispatch: u64,
+    next_frame: usize,
+}
+
+impl Replayer {
+    pub fn new(replay: Replay) -> Replayer {
+        Replayer {
+            replay,
+            dispatch: 0,
+            next_frame: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.dispatch >= self.replay.dispatches
+    }
+
+    pub fn next_batch(&mut self) -> Option<Vec<UIEvent>> {
+        if self.is_finished() {
+            return None;
+        }
+        let batch = match self.replay.frames.get(self.next_frame) {
+            Some(frame) if frame.dispatch == self.dispatch => {
+                self.next_frame += 1;
+                frame.events.clone()
+            }
+            _ => vec![],
+        };
+        self.dispatch += 1;
+        Some(batch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::game::{
+        common::UIEvent,
+        components::{damageable::Damageable, player_controlled::PlayerControlled},
+        simulation::Simulation,
+        world::{WorldParameters, WorldPosition, WorldTime},
+    };
+
+    use super::{Replay, ReplayError, SnapshotError, REPLAY_VERSION, SNAPSHOT_VERSION};
+
+    fn player_state(simulation: &Simulation) -> (Option<WorldPosition>, u32, u64) {
+        let world = &simulation.world;
+        let player_controlled = world.read_storage::<PlayerControlled>();
+        let damageable = world.read_storage::<Damageable>();
+        let world_position = world.read_storage::<WorldPosition>();
+        let (_, damageable, world_position) =
+            (&player_controlled, &damageable, (&world_position).maybe())
+                .join()
+                .next()
+                .unwrap();
+        (
+            world_position.copied(),
+            damageable.health,
+            world.read_resource::<WorldTime>().tick,
+        )
+    }
+
+    fn play(simulation: &mut Simulation) {
+        let moves = [UIEvent::Right, UIEvent::Down, UIEvent::Left, UIEvent::Up];
+        simulation.tick(vec![]);
+        for i in 0..200 {
+            if i % 3 == 0 {
+                simulation.tick(vec![moves[(i / 3) % 4], moves[(i / 7) % 4]]);
+            } else {
+                simulation.tick(vec![]);
+            }
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_the_run() {
+        let mut simulation = Simulation::new(
+            777,
+            WorldParameters {
+                width: 30,
+                height: 30,
+            },
+        );
+        play(&mut simulation);
+
+        let json = simulation.recording().to_json();
+        let replay = Replay::from_json(&json).unwrap();
+        assert_eq!(replay.dispatches, 201);
+
+        let replayed = replay.run();
+        assert_eq!(player_state(&replayed), player_state(&simulation));
+        assert_eq!(replayed.save().to_json(), simulation.save().to_json());
+    }
+
+    #[test]
+    fn replay_from_a_saved_game() {
+        let world_parameters = WorldParameters {
+            width: 30,
+            height: 30,
+        };
+        let mut original = Simulation::new(5, world_parameters.clone());
+        play(&mut original);
+
+        let mut resumed = Simulation::new(0, world_parameters);
+        resumed.load(&original.save());
+        play(&mut resumed);
+
+        let replayed = resumed.recording().run();
+        assert_eq!(player_state(&replayed), player_state(&resumed));
+    }
+
+    #[test]
+    fn live_events_are_ignored_while_replaying() {
+        let mut simulation = Simulation::new(
+            3,
+            WorldParameters {
+                width: 30,
+                height: 30,
+            },
+        );
+        play(&mut simulation);
+
+        let mut replayed = Simulation::from_replay(simulation.recording());
+        while replayed.is_replaying() {
+            replayed.tick(vec![UIEvent::Left]);
+        }
+        assert_eq!(player_state(&replayed), player_state(&simulation));
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut replay = Replay::new(
+            1,
+            WorldParameters {
+                width: 3,
+                height: 3,
+            },
+            None,
+        );
+        replay.version = REPLAY_VERSION + 1;
+        assert!(matches!(
+            Replay::from_json(&replay.to_json()),
+            Err(ReplayError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn stale_saved_games_are_rejected() {
+        let world_parameters = WorldParameters {
+            width: 3,
+            height: 3,
+        };
+        let mut start = Simulation::new(1, world_parameters.clone()).save();
+        start.version = SNAPSHOT_VERSION - 1;
+        let replay = Replay::new(1, world_parameters, Some(start));
+
+        match Replay::from_json(&replay.to_json()) {
+            Err(ReplayError::UnsupportedVersion(version)) => {
+                assert_eq!(version, SNAPSHOT_VERSION - 1)
+            }
+            _ => panic!("Expected the saved game's version to be rejected"),
+        }
+    }
+
+    #[test]
+    fn saved_games_with_dangling_entities_are_rejected() {
+        let world_parameters = WorldParameters {
+            width: 3,
+            height: 3,
+        };
+        let mut start = Simulation::new(1, world_parameters.clone()).save();
+        start.root = start.entities.len();
+        let replay = Replay::new(1, world_parameters, Some(start));
+
+        assert!(matches!(
+            Replay::from_json(&replay.to_json()),
+            Err(ReplayError::InvalidStart(SnapshotError::DanglingEntity(_)))
+        ));
+    }
+}
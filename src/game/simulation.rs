@@ -0,0 +1,225 @@
+use specs::prelude::*;
+
+use super::{
+    algorithms::pathfinding::Navigation,
+    camera::Camera,
+    common::UIEvent,
+    components::{
+        ai_controlled::AIControlled, armed::Armed, behaviour::Behaviour, climbable::Climbable,
+        collidable::Collidable, damageable::Damageable, describable::Describable,
+        equippable::Equippable, equipped::Equipped, factioned::Factioned, initiative::Initiative,
+        inventoried::Inventoried, level::Level, light_source::LightSource, movable::Movable,
+        opaque::Opaque, parent::Parent, pickupable::Pickupable,
+        player_controlled::PlayerControlled, regenerating::Regenerating, rendered::Render,
+        sighted::Sighted, stackable::Stackable, status_effects::StatusEffects, stowed::Stowed,
+        usable::Usable,
+    },
+    content::Content,
+    hierarchy::HierarchySystem,
+    light_map::LightMap,
+    message_log::MessageLog,
+    random::Randomness,
+    replay::{Replay, Replayer},
+    snapshot::Snapshot,
+    spatial_index::SpatialIndex,
+    systems::{
+        ai::AI, climbing::Climbing, combat::Combat, death::Death, effects::Effects,
+        framing::Framing, indexing::Indexing, inventory::Inventory,
+        level_generation::LevelGeneration, lighting::Lighting, looting::Looting,
+        messages::Messages, movement::Movement, perspective::Perspective,
+        player_command_handler::PlayerCommandHandler, recovery::Recovery, scheduling::Scheduling,
+    },
+    tile_map::TileMap,
+    world::{GameOver, LastUserEvent, UIState, WorldParameters, WorldPosition, WorldTime},
+};
+
+/// The platform independent part of the game: the specs world and the dispatcher running the
+/// game systems.
+///
+/// Frontends add their own systems (rendering, DOM updates) through `with_systems`, after the
+/// simulation systems they depend on.
+pub struct Simulation<'a, 'b> {
+    pub world: World,
+    pub root: Entity,
+    dispatcher: Dispatcher<'a, 'b>,
+    recording: Replay,
+    replayer: Option<Replayer>,
+}
+
+impl<'a, 'b> Simulation<'a, 'b> {
+    pub fn new(seed: u64, world_parameters: WorldParameters) -> Self {
+        Simulation::with_systems(seed, world_parameters, |builder| builder)
+    }
+
+    pub fn with_systems<F>(seed: u64, world_parameters: WorldParameters, add_systems: F) -> Self
+    where
+        F: FnOnce(DispatcherBuilder<'a, 'b>) -> DispatcherBuilder<'a, 'b>,
+    {
+        let recording = Replay::new(seed, world_parameters.clone(), None);
+
+        let mut world = World::new();
+        Simulation::register_components(&mut world);
+
+        world.insert(LastUserEvent::default());
+        world.insert(world_parameters);
+        world.insert(WorldTime::default());
+        world.insert(Randomness::from_seed(seed));
+        world.insert(UIState::default());
+        let spatial_index = SpatialIndex::new(&mut world);
+        world.insert(spatial_index);
+        world.insert(Navigation::default());
+        world.insert(Content::default());
+        world.insert(TileMap::default());
+        world.insert(LightMap::default());
+        world.insert(Camera::default());
+        world.insert(GameOver::default());
+        world.insert(MessageLog::default());
+
+        let builder = DispatcherBuilder::new()
+            .with(
+                HierarchySystem::<Parent>::new(&mut world),
+                "hierarchy_system",
+                &[],
+            )
+            .with(LevelGeneration {}, "level-generation", &[])
+            .with(Scheduling {}, "scheduling", &["level-generation"])
+            .with(
+                PlayerCommandHandler {},
+                "player-command-handling",
+                &["scheduling"],
+            )
+            .with(Indexing {}, "indexing", &["level-generation"])
+            .with(AI::default(), "ai", &["scheduling", "indexing"])
+            .with(Movement {}, "movement", &["player-command-handling"])
+            .with(Climbing {}, "climbing", &["movement"])
+            .with(Framing {}, "framing", &["movement", "climbing"])
+            .with(Combat {}, "combat", &["movement", "climbing"])
+            .with(Inventory {}, "inventory", &["player-command-handling"])
+            .with(Effects {}, "effects", &["scheduling"])
+            .with(Recovery {}, "recovery", &["effects"])
+            .with(Looting {}, "looting", &["climbing", "inventory"])
+            // After looting, so that nobody picks up the items of the dead in their last turn
+            .with(
+                Death::new(&mut world),
+                "death",
+                &["combat", "looting", "effects"],
+            )
+            .with(
+                Messages::new(&mut world),
+                "messages",
+                &["death", "climbing", "movement"],
+            )
+            .with(Lighting::default(), "lighting", &["death", "climbing"])
+            .with(Perspective::default(), "perspective", &["lighting"]);
+
+        let mut dispatcher = add_systems(builder).build();
+        dispatcher.setup(&mut world);
+
+        let root = world.create_entity().build();
+        world
+            .create_entity()
+            .with(Level::default())
+            .with(Parent { entity: root })
+            .build();
+
+        Simulation {
+            world,
+            root,
+            dispatcher,
+            recording,
+            replayer: None,
+        }
+    }
+
+    pub fn from_replay(replay: &Replay) -> Self {
+        Simulation::from_replay_with_systems(replay, |builder| builder)
+    }
+
+    /// Starts the simulation where the replay started. The next ticks play back the recorded
+    /// events instead of the ones they are given, until the replay is over.
+    pub fn from_replay_with_systems<F>(replay: &Replay, add_systems: F) -> Self
+    where
+        F: FnOnce(DispatcherBuilder<'a, 'b>) -> DispatcherBuilder<'a, 'b>,
+    {
+        let mut simulation =
+            Simulation::with_systems(replay.seed, replay.world_parameters.clone(), add_systems);
+        if let Some(start) = &replay.start {
+            simulation.load(start);
+        }
+        let replayer = Replayer::new(replay.clone());
+        if !replayer.is_finished() {
+            simulation.replayer = Some(replayer);
+        }
+        simulation
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replayer.is_some()
+    }
+
+    /// Every event batch dispatched since the game started or was loaded.
+    pub fn recording(&self) -> &Replay {
+        &self.recording
+    }
+
+    fn register_components(world: &mut World) {
+        world.register::<WorldPosition>();
+        world.register::<PlayerControlled>();
+        world.register::<Movable>();
+        world.register::<Render>();
+        world.register::<Level>();
+        world.register::<Collidable>();
+        world.register::<Pickupable>();
+        world.register::<Inventoried>();
+        world.register::<Factioned>();
+        world.register::<AIControlled>();
+        world.register::<Damageable>();
+        world.register::<Armed>();
+        world.register::<Sighted>();
+        world.register::<Opaque>();
+        world.register::<Describable>();
+        world.register::<Climbable>();
+        world.register::<Parent>();
+        world.register::<Initiative>();
+        world.register::<Behaviour>();
+        world.register::<Stowed>();
+        world.register::<LightSource>();
+        world.register::<Stackable>();
+        world.register::<Equippable>();
+        world.register::<Equipped>();
+        world.register::<Usable>();
+        world.register::<StatusEffects>();
+        world.register::<Regenerating>();
+    }
+
+    pub fn save(&self) -> Snapshot {
+        Snapshot::capture(&self.world, self.root)
+    }
+
+    /// Replaces the whole game state with the snapshot's. Systems keep running on the same
+    /// world, so frontend systems don't need to be rebuilt.
+    pub fn load(&mut self, snapshot: &Snapshot) {
+        self.root = snapshot.restore(&mut self.world);
+        self.recording = Replay::new(
+            snapshot.randomness.seed,
+            snapshot.world_parameters.clone(),
+            Some(snapshot.clone()),
+        );
+    }
+
+    /// Runs every system once with the events received since the last tick.
+    pub fn tick(&mut self, events: Vec<UIEvent>) {
+        let events = self
+            .replayer
+            .as_mut()
+            .and_then(Replayer::next_batch)
+            .unwrap_or(events);
+        if self.replayer.as_ref().is_some_and(Replayer::is_finished) {
+            self.replayer = None;
+        }
+        self.recording.record(&events);
+        self.world.write_resource::<LastUserEvent>().events = events;
+        self.dispatcher.dispatch(&self.world);
+        self.world.maintain();
+    }
+}
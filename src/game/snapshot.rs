@@ -0,0 +1,594 @@
+use std::{collections::HashMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+use super::{
+    components::{
+        ai_controlled::AIControlled,
+        armed::{Armed, DamageType},
+        behaviour::Behaviour,
+        climbable::Climbable,
+        collidable::Collidable,
+        damageable::Damageable,
+        describable::Describable,
+        equippable::Equippable,
+        equipped::Equipped,
+        factioned::Factioned,
+        initiative::Initiative,
+        inventoried::Inventoried,
+        level::Level,
+        light_source::LightSource,
+        movable::Movable,
+        opaque::Opaque,
+        parent::Parent,
+        pickupable::Pickupable,
+        player_controlled::PlayerControlled,
+        regenerating::Regenerating,
+        rendered::Render,
+        sighted::Sighted,
+        stackable::Stackable,
+        status_effects::{StatusEffect, StatusEffects},
+        stowed::Stowed,
+        usable::Usable,
+    },
+    hierarchy::{Hierarchy, ParentData},
+    random::Randomness,
+    tile_map::TileMap,
+    world::{GameOver, LastUserEvent, UIState, WorldParameters, WorldPosition, WorldTime},
+};
+
+/// Bumped whenever the snapshot layout changes, even for fields only added, and older snapshots
+/// are rejected rather than read with defaults filled in for what they lack.
+pub const SNAPSHOT_VERSION: u32 = 14;
+
+/// Index of an entity in `Snapshot::entities`, stored in place of `Entity` references since
+/// entities get new ids when a snapshot is loaded.
+pub type SnapshotEntity = usize;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Malformed(serde_json::Error),
+    UnsupportedVersion(u32),
+    /// An entity reference pointing past the end of `Snapshot::entities`.
+    DanglingEntity(SnapshotEntity),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Malformed(error) => write!(f, "Malformed snapshot: {}", error),
+            SnapshotError::UnsupportedVersion(version) => write!(
+                f,
+                "Snapshot version {} is not supported (expected {})",
+                version, SNAPSHOT_VERSION
+            ),
+            SnapshotError::DanglingEntity(index) => {
+                write!(f, "Snapshot refers to missing entity {}", index)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ArmedSnapshot {
+    pub damage: u32,
+    pub damage_type: DamageType,
+    pub accuracy: u32,
+    pub critical_chance: f64,
+    pub inflicts: Option<StatusEffect>,
+    pub targetting: Option<SnapshotEntity>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SightedSnapshot {
+    pub radius: u64,
+    pub seen: Vec<SnapshotEntity>,
+    pub seen_recently: Vec<SnapshotEntity>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// Every component of a single entity.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct EntitySnapshot {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub world_position: Option<WorldPosition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player_controlled: Option<PlayerControlled>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movable: Option<Movable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub render: Option<Render>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<Level>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inventoried: Option<Inventoried>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub factioned: Option<Factioned>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub damageable: Option<Damageable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub armed: Option<ArmedSnapshot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sighted: Option<SightedSnapshot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub describable: Option<Describable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initiative: Option<Initiative>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub behaviour: Option<Behaviour>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub climbable: Option<Climbable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stowed: Option<Stowed>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light_source: Option<LightSource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stackable: Option<Stackable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usable: Option<Usable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equippable: Option<Equippable>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_effects: Option<StatusEffects>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regenerating: Option<Regenerating>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<SnapshotEntity>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub collidable: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub pickupable: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub ai_controlled: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub opaque: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub equipped: bool,
+}
+
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    version: u32,
+}
+
+/// Serializable copy of the whole game state.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    pub version: u32,
+    pub root: SnapshotEntity,
+    pub world_time: WorldTime,
+    pub world_parameters: WorldParameters,
+    pub randomness: Randomness,
+    /// Terrain of the level the player is on, the other levels keep theirs in `Level`.
+    pub tile_map: TileMap,
+    pub game_over: GameOver,
+    pub entities: Vec<EntitySnapshot>,
+}
+
+impl Snapshot {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Snapshot, SnapshotError> {
+        let header: SnapshotHeader =
+            serde_json::from_str(json).map_err(SnapshotError::Malformed)?;
+        if header.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(header.version));
+        }
+        let snapshot: Snapshot = serde_json::from_str(json).map_err(SnapshotError::Malformed)?;
+        snapshot.check_entities()?;
+        Ok(snapshot)
+    }
+
+    /// Makes sure every entity reference is an index into `entities`, which `restore` relies on.
+    pub(crate) fn check_entities(&self) -> Result<(), SnapshotError> {
+        let references = self.entities.iter().flat_map(|entity| {
+            let armed = entity.armed.iter().filter_map(|armed| armed.targetting);
+            let sighted = entity
+                .sighted
+                .iter()
+                .flat_map(|sighted| sighted.seen.iter().chain(&sighted.seen_recently).copied());
+            entity.parent.into_iter().chain(armed).chain(sighted)
+        });
+        match std::iter::once(self.root)
+            .chain(references)
+            .find(|index| *index >= self.entities.len())
+        {
+            Some(index) => Err(SnapshotError::DanglingEntity(index)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn capture(world: &World, root: Entity) -> Snapshot {
+        let entities = world.entities();
+        let all_entities: Vec<Entity> = (&entities).join().collect();
+        let indices: HashMap<Entity, SnapshotEntity> = all_entities
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| (*entity, index))
+            .collect();
+        let index_of_id = |id: u32| indices.get(&entities.entity(id)).copied();
+
+        let world_position = world.read_storage::<WorldPosition>();
+        let player_controlled = world.read_storage::<PlayerControlled>();
+        let movable = world.read_storage::<Movable>();
+        let render = world.read_storage::<Render>();
+        let level = world.read_storage::<Level>();
+        let inventoried = world.read_storage::<Inventoried>();
+        let factioned = world.read_storage::<Factioned>();
+        let damageable = world.read_storage::<Damageable>();
+        let armed = world.read_storage::<Armed>();
+        let sighted = world.read_storage::<Sighted>();
+        let describable = world.read_storage::<Describable>();
+        let initiative = world.read_storage::<Initiative>();
+        let behaviour = world.read_storage::<Behaviour>();
+        let parent = world.read_storage::<Parent>();
+        let collidable = world.read_storage::<Collidable>();
+        let pickupable = world.read_storage::<Pickupable>();
+        let ai_controlled = world.read_storage::<AIControlled>();
+        let opaque = world.read_storage::<Opaque>();
+        let climbable = world.read_storage::<Climbable>();
+        let stowed = world.read_storage::<Stowed>();
+        let light_source = world.read_storage::<LightSource>();
+        let stackable = world.read_storage::<Stackable>();
+        let usable = world.read_storage::<Usable>();
+        let equippable = world.read_storage::<Equippable>();
+        let equipped = world.read_storage::<Equipped>();
+        let status_effects = world.read_storage::<StatusEffects>();
+        let regenerating = world.read_storage::<Regenerating>();
+
+        let entity_snapshots = all_entities
+            .iter()
+            .map(|entity| {
+                let entity = *entity;
+                EntitySnapshot {
+                    world_position: world_position.get(entity).copied(),
+                    player_controlled: player_controlled.get(entity).cloned(),
+                    movable: movable.get(entity).cloned(),
+                    render: render.get(entity).cloned(),
+                    level: level.get(entity).cloned(),
+                    inventoried: inventoried.get(entity).cloned(),
+                    factioned: factioned.get(entity).cloned(),
+                    damageable: damageable.get(entity).cloned(),
+                    armed: armed.get(entity).map(|armed| ArmedSnapshot {
+                        damage: armed.damage,
+                        damage_type: armed.damage_type,
+                        accuracy: armed.accuracy,
+                        critical_chance: armed.critical_chance,
+                        inflicts: armed.inflicts,
+                        targetting: armed.targetting.and_then(|t| indices.get(&t).copied()),
+                    }),
+                    sighted: sighted.get(entity).map(|sighted| SightedSnapshot {
+                        radius: sighted.radius,
+                        seen: (&sighted.seen).join().filter_map(index_of_id).collect(),
+                        seen_recently: (&sighted.seen_recently)
+                            .join()
+                            .filter_map(index_of_id)
+                            .collect(),
+                    }),
+                    describable: describable.get(entity).cloned(),
+                    initiative: initiative.get(entity).cloned(),
+                    behaviour: behaviour.get(entity).cloned(),
+                    climbable: climbable.get(entity).copied(),
+                    stowed: stowed.get(entity).cloned(),
+                    light_source: light_source.get(entity).copied(),
+                    stackable: stackable.get(entity).cloned(),
+                    usable: usable.get(entity).cloned(),
+                    equippable: equippable.get(entity).cloned(),
+                    status_effects: status_effects.get(entity).cloned(),
+                    regenerating: regenerating.get(entity).cloned(),
+                    parent: parent
+                        .get(entity)
+                        .and_then(|parent| indices.get(&parent.entity).copied()),
+                    collidable: collidable.contains(entity),
+                    pickupable: pickupable.contains(entity),
+                    ai_controlled: ai_controlled.contains(entity),
+                    opaque: opaque.contains(entity),
+                    equipped: equipped.contains(entity),
+                }
+            })
+            .collect();
+
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            root: indices[&root],
+            world_time: (*world.read_resource::<WorldTime>()).clone(),
+            world_parameters: (*world.read_resource::<WorldParameters>()).clone(),
+            randomness: (*world.read_resource::<Randomness>()).clone(),
+            tile_map: (*world.read_resource::<TileMap>()).clone(),
+            game_over: (*world.read_resource::<GameOver>()).clone(),
+            entities: entity_snapshots,
+        }
+    }
+
+    /// Replaces every entity and game resource of the world with the snapshot's, returning the
+    /// new root entity.
+    pub fn restore(&self, world: &mut World) -> Entity {
+        world.delete_all();
+        world.maintain();
+        // Flush the removals now, so they are not mixed up with the insertions of new entities
+        // reusing the same ids.
+        world
+            .write_resource::<Hierarchy<Parent>>()
+            .maintain(world.system_data::<ParentData<Parent>>());
+
+        world.insert(self.world_time.clone());
+        world.insert(self.world_parameters.clone());
+        world.insert(self.randomness.clone());
+        world.insert(self.tile_map.clone());
+        world.insert(self.game_over.clone());
+        world.insert(LastUserEvent::default());
+        world.insert(UIState::default());
+
+        let entities: Vec<Entity> = self
+            .entities
+            .iter()
+            .map(|_| world.create_entity().build())
+            .collect();
+
+        let mut world_position = world.write_storage::<WorldPosition>();
+        let mut player_controlled = world.write_storage::<PlayerControlled>();
+        let mut movable = world.write_storage::<Movable>();
+        let mut render = world.write_storage::<Render>();
+        let mut level = world.write_storage::<Level>();
+        let mut inventoried = world.write_storage::<Inventoried>();
+        let mut factioned = world.write_storage::<Factioned>();
+        let mut damageable = world.write_storage::<Damageable>();
+        let mut armed = world.write_storage::<Armed>();
+        let mut sighted = world.write_storage::<Sighted>();
+        let mut describable = world.write_storage::<Describable>();
+        let mut initiative = world.write_storage::<Initiative>();
+        let mut behaviour = world.write_storage::<Behaviour>();
+        let mut parent = world.write_storage::<Parent>();
+        let mut collidable = world.write_storage::<Collidable>();
+        let mut pickupable = world.write_storage::<Pickupable>();
+        let mut ai_controlled = world.write_storage::<AIControlled>();
+        let mut opaque = world.write_storage::<Opaque>();
+        let mut climbable = world.write_storage::<Climbable>();
+        let mut stowed = world.write_storage::<Stowed>();
+        let mut light_source = world.write_storage::<LightSource>();
+        let mut stackable = world.write_storage::<Stackable>();
+        let mut usable = world.write_storage::<Usable>();
+        let mut equippable = world.write_storage::<Equippable>();
+        let mut equipped = world.write_storage::<Equipped>();
+        let mut status_effects = world.write_storage::<StatusEffects>();
+        let mut regenerating = world.write_storage::<Regenerating>();
+
+        let entity_at = |index: &SnapshotEntity| entities.get(*index).copied();
+
+        for (entity, snapshot) in entities.iter().zip(self.entities.iter()) {
+            let entity = *entity;
+            if let Some(position) = snapshot.world_position {
+                world_position.insert(entity, position).unwrap();
+            }
+            if let Some(c) = snapshot.player_controlled.clone() {
+                player_controlled.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.movable.clone() {
+                movable.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.render.clone() {
+                render.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.level.clone() {
+                level.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.inventoried.clone() {
+                inventoried.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.factioned.clone() {
+                factioned.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.damageable.clone() {
+                damageable.insert(entity, c).unwrap();
+            }
+            if let Some(c) = &snapshot.armed {
+                let c = Armed {
+                    damage: c.damage,
+                    damage_type: c.damage_type,
+                    accuracy: c.accuracy,
+                    critical_chance: c.critical_chance,
+                    inflicts: c.inflicts,
+                    targetting: c.targetting.as_ref().and_then(entity_at),
+                };
+                armed.insert(entity, c).unwrap();
+            }
+            if let Some(c) = &snapshot.sighted {
+                let mut c2 = Sighted::new(c.radius);
+                for seen in c.seen.iter().filter_map(entity_at) {
+                    c2.seen.add(seen.id());
+                }
+                for seen in c.seen_recently.iter().filter_map(entity_at) {
+                    c2.seen_recently.add(seen.id());
+                }
+                sighted.insert(entity, c2).unwrap();
+            }
+            if let Some(c) = snapshot.describable.clone() {
+                describable.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.initiative.clone() {
+                initiative.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.behaviour.clone() {
+                behaviour.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.climbable {
+                climbable.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.stowed.clone() {
+                stowed.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.light_source {
+                light_source.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.stackable.clone() {
+                stackable.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.usable.clone() {
+                usable.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.equippable.clone() {
+                equippable.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.status_effects.clone() {
+                status_effects.insert(entity, c).unwrap();
+            }
+            if let Some(c) = snapshot.regenerating.clone() {
+                regenerating.insert(entity, c).unwrap();
+            }
+            if let Some(parent_entity) = snapshot.parent.as_ref().and_then(entity_at) {
+                parent
+                    .insert(
+                        entity,
+                        Parent {
+                            entity: parent_entity,
+                        },
+                    )
+                    .unwrap();
+            }
+            if snapshot.collidable {
+                collidable.insert(entity, Collidable).unwrap();
+            }
+            if snapshot.pickupable {
+                pickupable.insert(entity, Pickupable).unwrap();
+            }
+            if snapshot.ai_controlled {
+                ai_controlled.insert(entity, AIControlled).unwrap();
+            }
+            if snapshot.opaque {
+                opaque.insert(entity, Opaque).unwrap();
+            }
+            if snapshot.equipped {
+                equipped.insert(entity, Equipped).unwrap();
+            }
+        }
+
+        entities[self.root]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::game::{
+        common::UIEvent,
+        components::{
+            ai_controlled::AIControlled, pickupable::Pickupable,
+            player_controlled::PlayerControlled,
+        },
+        random::Randomness,
+        test_harness::TestWorld,
+        tile_map::Tile,
+        world::{WorldParameters, WorldTime},
+    };
+
+    use super::{Snapshot, SnapshotError, SNAPSHOT_VERSION};
+
+    fn find<C: Component>(test_world: &TestWorld) -> Entity {
+        let world = &test_world.simulation.world;
+        (&world.entities(), &world.read_storage::<C>())
+            .join()
+            .map(|(entity, _)| entity)
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn round_trip_keeps_state_and_relationships() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let monster = test_world.monster(8, 5, 10, 1);
+        test_world.gold(5, 6);
+        test_world.wall(1, 1);
+        test_world.press(UIEvent::Down);
+        test_world.press(UIEvent::Up);
+
+        let tick = test_world
+            .simulation
+            .world
+            .read_resource::<WorldTime>()
+            .tick;
+        let json = test_world.simulation.save().to_json();
+        let snapshot = Snapshot::from_json(&json).unwrap();
+
+        let mut loaded = TestWorld::new(3, 3);
+        loaded.simulation.load(&snapshot);
+
+        let world = &loaded.simulation.world;
+        assert_eq!(world.read_resource::<WorldTime>().tick, tick);
+        assert_eq!(world.read_resource::<WorldParameters>().width, 10);
+        assert_eq!(
+            world.read_resource::<Randomness>().seed,
+            test_world
+                .simulation
+                .world
+                .read_resource::<Randomness>()
+                .seed
+        );
+
+        let loaded_player = find::<PlayerControlled>(&loaded);
+        let loaded_monster = find::<AIControlled>(&loaded);
+        assert_eq!(loaded.position(loaded_player), test_world.position(player));
+        assert_eq!(loaded.health(loaded_player), test_world.health(player));
+        assert_eq!(
+            loaded.position(loaded_monster),
+            test_world.position(monster)
+        );
+        assert_eq!(loaded.health(loaded_monster), test_world.health(monster));
+
+        let gold = find::<Pickupable>(&loaded);
+        assert_eq!(loaded.parent(gold), Some(loaded_player));
+        assert_eq!(loaded.position(gold), None);
+        assert_eq!(loaded.parent(loaded_monster), Some(loaded.level()));
+        assert_eq!(loaded.parent(loaded.level()), Some(loaded.simulation.root));
+        assert!(loaded.sees(loaded_player, loaded_monster));
+        assert_eq!(loaded.tile(1, 1), Some(Tile::StoneWall));
+        assert_eq!(loaded.tile(1, 2), Some(Tile::Floor));
+
+        // The hierarchy is rebuilt from the restored `Parent` components
+        loaded.send(vec![]);
+        assert_eq!(loaded.children(loaded_player), vec![gold]);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let mut test_world = TestWorld::new(3, 3);
+        test_world.player(1, 1);
+        let mut snapshot = test_world.simulation.save();
+        snapshot.version = SNAPSHOT_VERSION + 1;
+
+        match Snapshot::from_json(&snapshot.to_json()) {
+            Err(SnapshotError::UnsupportedVersion(version)) => {
+                assert_eq!(version, SNAPSHOT_VERSION + 1)
+            }
+            _ => panic!("Expected the version to be rejected"),
+        }
+    }
+
+    #[test]
+    fn dangling_entities_are_rejected() {
+        let mut test_world = TestWorld::new(3, 3);
+        test_world.player(1, 1);
+        test_world.send(vec![]);
+        let mut snapshot = test_world.simulation.save();
+        let missing = snapshot.entities.len();
+        let player_snapshot = snapshot
+            .entities
+            .iter_mut()
+            .find(|entity| entity.player_controlled.is_some())
+            .unwrap();
+        player_snapshot.sighted.as_mut().unwrap().seen.push(missing);
+
+        match Snapshot::from_json(&snapshot.to_json()) {
+            Err(SnapshotError::DanglingEntity(index)) => assert_eq!(index, missing),
+            _ => panic!("Expected the missing entity to be rejected"),
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::Deref,
+};
+
+use specs::{
+    prelude::*,
+    storage::{MaskedStorage, Storage},
+    world::Index,
+};
+
+use super::{
+    algorithms::generation::Rect, components::collidable::Collidable, tile_map::TileMap,
+    world::WorldPosition,
+};
+
+/// Which entities are where, for the entities having a `WorldPosition`.
+///
+/// The index follows the events of the flagged `WorldPosition` storage, so systems only ever
+/// insert, change or remove positions. `Indexing` catches it up once per dispatch for the systems
+/// reading it, while the systems changing positions go through `SpatialData`, which catches up
+/// with their own changes before handing it out.
+pub struct SpatialIndex {
+    cells: HashMap<WorldPosition, BTreeSet<Entity>>,
+    positions: HashMap<Index, (Entity, WorldPosition)>,
+    reader_id: ReaderId<ComponentEvent>,
+}
+
+impl SpatialIndex {
+    pub fn new(world: &mut World) -> SpatialIndex {
+        SpatialIndex {
+            cells: HashMap::new(),
+            positions: HashMap::new(),
+            reader_id: world.write_storage::<WorldPosition>().register_reader(),
+        }
+    }
+
+    /// Catches up with the positions inserted, changed or removed since the last call.
+    pub fn maintain<D>(&mut self, entities: &Entities, world_position: &Storage<WorldPosition, D>)
+    where
+        D: Deref<Target = MaskedStorage<WorldPosition>>,
+    {
+        let mut changed = BitSet::new();
+        for event in world_position.channel().read(&mut self.reader_id) {
+            match event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Modified(id)
+                | ComponentEvent::Removed(id) => {
+                    changed.add(*id);
+                }
+            }
+        }
+
+        // Only the latest position matters, however many times an entity moved since
+        for id in (&changed).join() {
+            self.forget(id);
+            let entity = entities.entity(id);
+            if let Some(position) = world_position.get(entity) {
+                if entities.is_alive(entity) {
+                    self.positions.insert(id, (entity, *position));
+                    self.cells.entry(*position).or_default().insert(entity);
+                }
+            }
+        }
+    }
+
+    fn forget(&mut self, id: Index) {
+        if let Some((entity, position)) = self.positions.remove(&id) {
+            if let Some(cell) = self.cells.get_mut(&position) {
+                cell.remove(&entity);
+                if cell.is_empty() {
+                    self.cells.remove(&position);
+                }
+            }
+        }
+    }
+
+    pub fn position_of(&self, entity: Entity) -> Option<WorldPosition> {
+        self.positions
+            .get(&entity.id())
+            .filter(|(indexed, _)| *indexed == entity)
+            .map(|(_, position)| *position)
+    }
+
+    /// The entities in the cell, always in the same order.
+    pub fn entities_at(&self, world_position: WorldPosition) -> impl Iterator<Item = Entity> + '_ {
+        self.cells
+            .get(&world_position)
+            .into_iter()
+            .flat_map(|cell| cell.iter().copied())
+    }
+
+    /// The first entity in the cell with the component, along with the component.
+    pub fn find_at<'s, C, D>(
+        &self,
+        world_position: WorldPosition,
+        storage: &'s Storage<C, D>,
+    ) -> Option<(Entity, &'s C)>
+    where
+        C: Component,
+        D: Deref<Target = MaskedStorage<C>>,
+    {
+        self.entities_at(world_position)
+            .find_map(|entity| storage.get(entity).map(|component| (entity, component)))
+    }
+
+    pub fn any_at<C, D>(&self, world_position: WorldPosition, storage: &Storage<C, D>) -> bool
+    where
+        C: Component,
+        D: Deref<Target = MaskedStorage<C>>,
+    {
+        self.find_at(world_position, storage).is_some()
+    }
+
+    /// Whether the terrain or a collidable entity is in the way.
+    pub fn is_blocked<D>(
+        &self,
+        world_position: WorldPosition,
+        tile_map: &TileMap,
+        collidable: &Storage<Collidable, D>,
+    ) -> bool
+    where
+        D: Deref<Target = MaskedStorage<Collidable>>,
+    {
+        !tile_map.is_walkable(world_position) || self.any_at(world_position, collidable)
+    }
+
+    /// Entities at most `radius` cells away, closest first.
+    pub fn in_radius(&self, center: WorldPosition, radius: u64) -> Vec<Entity> {
+        let area = Rect {
+            x: center.x.saturating_sub(radius),
+            y: center.y.saturating_sub(radius),
+            width: center.x.min(radius) + radius + 1,
+            height: center.y.min(radius) + radius + 1,
+        };
+        let mut found: Vec<(u64, Entity)> = area
+            .positions()
+            .filter(|position| position.distance_from(center) <= radius as f64)
+            .flat_map(|position| {
+                let distance =
+                    position.x.abs_diff(center.x).pow(2) + position.y.abs_diff(center.y).pow(2);
+                self.entities_at(position)
+                    .map(move |entity| (distance, entity))
+            })
+            .collect();
+        found.sort();
+        found.into_iter().map(|(_, entity)| entity).collect()
+    }
+
+    /// Entities within the rectangle, column by column.
+    pub fn in_rect(&self, rect: &Rect) -> Vec<Entity> {
+        rect.positions()
+            .flat_map(|position| self.entities_at(position))
+            .collect()
+    }
+
+    /// The closest entity with the component, ties going to the entity created first.
+    pub fn nearest_with<C, D>(
+        &self,
+        from: WorldPosition,
+        storage: &Storage<C, D>,
+    ) -> Option<(Entity, WorldPosition)>
+    where
+        C: Component,
+        D: Deref<Target = MaskedStorage<C>>,
+    {
+        self.positions
+            .values()
+            .filter(|(entity, _)| storage.contains(*entity))
+            .min_by_key(|(entity, position)| {
+                let distance =
+                    position.x.abs_diff(from.x).pow(2) + position.y.abs_diff(from.y).pow(2);
+                (distance, *entity)
+            })
+            .copied()
+    }
+
+    /// Every occupied cell with the entities in it.
+    pub fn cells(&self) -> impl Iterator<Item = (WorldPosition, &BTreeSet<Entity>)> {
+        self.cells.iter().map(|(position, cell)| (*position, cell))
+    }
+}
+
+/// The positions of the entities along with the index of them, for the systems changing positions.
+#[derive(SystemData)]
+pub struct SpatialData<'a> {
+    pub entities: Entities<'a>,
+    pub world_position: WriteStorage<'a, WorldPosition>,
+    spatial_index: WriteExpect<'a, SpatialIndex>,
+}
+
+impl<'a> SpatialData<'a> {
+    /// The index, up to date with every position changed so far.
+    pub fn index(&mut self) -> &SpatialIndex {
+        self.split().2
+    }
+
+    /// The up to date index, for systems also going through or changing positions while they
+    /// look it up. It has to be split again after positions changed.
+    pub fn split(
+        &mut self,
+    ) -> (
+        &Entities<'a>,
+        &mut WriteStorage<'a, WorldPosition>,
+        &SpatialIndex,
+    ) {
+        self.spatial_index
+            .maintain(&self.entities, &self.world_position);
+        (
+            &self.entities,
+            &mut self.world_position,
+            &self.spatial_index,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::game::{
+        algorithms::generation::Rect,
+        components::{collidable::Collidable, pickupable::Pickupable},
+        test_harness::TestWorld,
+        tile_map::TileMap,
+        world::WorldPosition,
+    };
+
+    use super::SpatialData;
+
+    fn spatial_data(test_world: &TestWorld) -> SpatialData<'_> {
+        test_world.simulation.world.system_data::<SpatialData>()
+    }
+
+    #[test]
+    fn the_index_follows_positions() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(2, 2);
+        let gold = test_world.gold(2, 2);
+        assert_eq!(
+            spatial_data(&test_world)
+                .index()
+                .entities_at(WorldPosition { x: 2, y: 2 })
+                .collect::<Vec<_>>(),
+            vec![player, gold]
+        );
+
+        test_world.teleport(player, 5, 5);
+        test_world
+            .simulation
+            .world
+            .write_storage::<WorldPosition>()
+            .remove(gold);
+
+        let mut spatial_data = spatial_data(&test_world);
+        let spatial_index = spatial_data.index();
+        assert_eq!(
+            spatial_index.position_of(player),
+            Some(WorldPosition { x: 5, y: 5 })
+        );
+        assert_eq!(spatial_index.position_of(gold), None);
+        assert_eq!(
+            spatial_index
+                .entities_at(WorldPosition { x: 2, y: 2 })
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn deleted_entities_leave_the_index() {
+        let mut test_world = TestWorld::new(10, 10);
+        let monster = test_world.monster(3, 3, 1, 1);
+        spatial_data(&test_world).index();
+
+        test_world.simulation.world.delete_entity(monster).unwrap();
+        test_world.simulation.world.maintain();
+
+        assert_eq!(spatial_data(&test_world).index().position_of(monster), None);
+    }
+
+    #[test]
+    fn area_queries() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let near = test_world.gold(6, 6);
+        let far = test_world.gold(9, 5);
+        let monster = test_world.monster(5, 3, 10, 1);
+        test_world.wall(4, 5);
+
+        let mut spatial_data = spatial_data(&test_world);
+        let spatial_index = spatial_data.index();
+        let world = &test_world.simulation.world;
+        let center = WorldPosition { x: 5, y: 5 };
+
+        assert_eq!(
+            spatial_index.in_radius(center, 2),
+            vec![player, near, monster]
+        );
+        assert_eq!(
+            spatial_index.in_rect(&Rect {
+                x: 5,
+                y: 5,
+                width: 5,
+                height: 2,
+            }),
+            vec![player, near, far]
+        );
+        assert_eq!(
+            spatial_index.nearest_with(center, &world.read_storage::<Pickupable>()),
+            Some((near, WorldPosition { x: 6, y: 6 }))
+        );
+
+        let tile_map = world.read_resource::<TileMap>();
+        let collidable = world.read_storage::<Collidable>();
+        assert!(spatial_index.is_blocked(WorldPosition { x: 4, y: 5 }, &tile_map, &collidable));
+        assert!(spatial_index.is_blocked(WorldPosition { x: 5, y: 3 }, &tile_map, &collidable));
+        assert!(!spatial_index.is_blocked(WorldPosition { x: 6, y: 6 }, &tile_map, &collidable));
+    }
+}
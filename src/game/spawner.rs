@@ -0,0 +1,211 @@
+use specs::prelude::*;
+
+use super::{
+    components::{
+        ai_controlled::AIControlled, armed::Armed, behaviour::Behaviour, climbable::Climbable,
+        collidable::Collidable, damageable::Damageable, describable::Describable,
+        equippable::Equippable, factioned::Factioned, initiative::Initiative,
+        inventoried::Inventoried, light_source::LightSource, movable::Movable, opaque::Opaque,
+        parent::Parent, pickupable::Pickupable, player_controlled::PlayerControlled,
+        regenerating::Regenerating, rendered::Render, sighted::Sighted, stackable::Stackable,
+        usable::Usable,
+    },
+    content::{Content, EntityTemplate},
+    world::WorldPosition,
+};
+
+/// Builds entities from the templates in `Content`.
+///
+/// Systems that spawn entities take it as part of their `SystemData`, and reach the storages it
+/// holds through it since they can't borrow them a second time.
+#[derive(SystemData)]
+pub struct Spawner<'a> {
+    pub entities: Entities<'a>,
+    pub content: Read<'a, Content>,
+    pub world_position: WriteStorage<'a, WorldPosition>,
+    pub parent: WriteStorage<'a, Parent>,
+    pub render: WriteStorage<'a, Render>,
+    pub describable: WriteStorage<'a, Describable>,
+    pub player_controlled: WriteStorage<'a, PlayerControlled>,
+    pub ai_controlled: WriteStorage<'a, AIControlled>,
+    pub behaviour: WriteStorage<'a, Behaviour>,
+    pub movable: WriteStorage<'a, Movable>,
+    pub collidable: WriteStorage<'a, Collidable>,
+    pub pickupable: WriteStorage<'a, Pickupable>,
+    pub inventoried: WriteStorage<'a, Inventoried>,
+    pub factioned: WriteStorage<'a, Factioned>,
+    pub damageable: WriteStorage<'a, Damageable>,
+    pub regenerating: WriteStorage<'a, Regenerating>,
+    pub armed: WriteStorage<'a, Armed>,
+    pub sighted: WriteStorage<'a, Sighted>,
+    pub opaque: WriteStorage<'a, Opaque>,
+    pub climbable: WriteStorage<'a, Climbable>,
+    pub light_source: WriteStorage<'a, LightSource>,
+    pub stackable: WriteStorage<'a, Stackable>,
+    pub usable: WriteStorage<'a, Usable>,
+    pub equippable: WriteStorage<'a, Equippable>,
+    pub initiative: WriteStorage<'a, Initiative>,
+}
+
+impl<'a> Spawner<'a> {
+    /// Spawns the named template at the given position, or returns `None` if there is no such
+    /// template.
+    pub fn spawn(
+        &mut self,
+        name: &str,
+        position: WorldPosition,
+        parent: Option<Entity>,
+    ) -> Option<Entity> {
+        let template = match self.content.template(name) {
+            Some(template) => template.clone(),
+            None => {
+                log::warn!("No entity template named {}", name);
+                return None;
+            }
+        };
+        Some(self.spawn_template(name, &template, position, parent))
+    }
+
+    /// `name` tells stacks apart, only items spawned under the same name stack together.
+    pub fn spawn_template(
+        &mut self,
+        name: &str,
+        template: &EntityTemplate,
+        position: WorldPosition,
+        parent: Option<Entity>,
+    ) -> Entity {
+        let mut builder = self.entities.build_entity();
+        if let Some(parent) = parent {
+            builder = builder.with(Parent { entity: parent }, &mut self.parent);
+        }
+        if let Some(description) = &template.description {
+            builder = builder.with(
+                Describable {
+                    description: description.clone(),
+                },
+                &mut self.describable,
+            );
+        }
+        if let Some(render) = &template.render {
+            builder = builder.with(render.clone(), &mut self.render);
+        }
+        if let Some(faction) = &template.faction {
+            builder = builder.with(
+                Factioned {
+                    faction: faction.clone(),
+                },
+                &mut self.factioned,
+            );
+        }
+        if let Some(health) = template.health {
+            builder = builder.with(
+                Damageable {
+                    defense: template.defense,
+                    armor: template.armor,
+                    resistances: template.resistances.clone(),
+                    vulnerabilities: template.vulnerabilities.clone(),
+                    ..Damageable::new(health)
+                },
+                &mut self.damageable,
+            );
+        }
+        if let Some(turns_per_point) = template.regeneration {
+            builder = builder.with(Regenerating::new(turns_per_point), &mut self.regenerating);
+        }
+        if let Some(damage) = template.damage {
+            builder = builder.with(
+                Armed {
+                    damage,
+                    damage_type: template.damage_type,
+                    accuracy: template.accuracy,
+                    critical_chance: template.critical_chance,
+                    inflicts: template.inflicts,
+                    targetting: None,
+                },
+                &mut self.armed,
+            );
+        }
+        if let Some(speed) = template.speed {
+            builder = builder.with(Initiative::new(speed), &mut self.initiative);
+        }
+        if template.player_controlled {
+            builder = builder.with(PlayerControlled::default(), &mut self.player_controlled);
+        }
+        if template.ai_controlled {
+            builder = builder
+                .with(AIControlled, &mut self.ai_controlled)
+                .with(Behaviour::default(), &mut self.behaviour);
+        }
+        if template.movable {
+            builder = builder.with(Movable::default(), &mut self.movable);
+        }
+        if let Some(capacity) = template.inventory_capacity {
+            builder = builder.with(Inventoried::new(capacity), &mut self.inventoried);
+        }
+        if template.collidable {
+            builder = builder.with(Collidable, &mut self.collidable);
+        }
+        if template.opaque {
+            builder = builder.with(Opaque, &mut self.opaque);
+        }
+        if template.pickupable {
+            builder = builder.with(Pickupable, &mut self.pickupable);
+        }
+        if template.stackable {
+            builder = builder.with(Stackable::new(name), &mut self.stackable);
+        }
+        if let Some(usable) = &template.usable {
+            builder = builder.with(usable.clone(), &mut self.usable);
+        }
+        if let Some(equippable) = &template.equippable {
+            builder = builder.with(equippable.clone(), &mut self.equippable);
+        }
+        if let Some(climbable) = template.climbable {
+            builder = builder.with(climbable, &mut self.climbable);
+        }
+        if let Some(light_source) = template.light_source {
+            builder = builder.with(light_source, &mut self.light_source);
+        }
+        if let Some(radius) = template.sight_radius {
+            builder = builder.with(Sighted::new(radius), &mut self.sighted);
+        }
+        let entity = builder.build();
+        self.place(entity, position);
+        entity
+    }
+
+    /// Moves an existing entity.
+    pub fn place(&mut self, entity: Entity, position: WorldPosition) {
+        self.world_position.insert(entity, position).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use super::Spawner;
+    use crate::game::{test_harness::TestWorld, world::WorldPosition};
+
+    #[test]
+    fn spawned_entities_get_the_template_components() {
+        let mut test_world = TestWorld::new(5, 5);
+        let level = test_world.level();
+        let position = WorldPosition { x: 2, y: 3 };
+
+        let (monster, missing) = {
+            let mut spawner = test_world.simulation.world.system_data::<Spawner>();
+            (
+                spawner.spawn("goblin", position, level.into()).unwrap(),
+                spawner.spawn("dragon", position, level.into()),
+            )
+        };
+        test_world.simulation.world.maintain();
+
+        assert!(missing.is_none());
+        assert_eq!(test_world.position(monster), Some(position));
+        assert_eq!(test_world.health(monster), Some(10));
+        assert_eq!(test_world.parent(monster), Some(level));
+        assert_eq!(test_world.entities_at(2, 3), vec![monster]);
+    }
+}
@@ -1,57 +1,325 @@
 use specs::prelude::*;
 
 use crate::game::{
+    algorithms::pathfinding::{a_star, Navigation, Obstacles},
     components::{
         ai_controlled::AIControlled,
+        behaviour::{AIState, Behaviour},
+        collidable::Collidable,
+        damageable::Damageable,
         factioned::{Faction, Factioned},
+        initiative::{Action, Initiative},
+        level::Level,
         movable::{Direction, Movable},
+        sighted::Sighted,
     },
-    world::{WorldPosition, WorldTime},
+    random::{RandomGenerator, Randomness},
+    spatial_index::SpatialIndex,
+    tile_map::TileMap,
+    world::WorldPosition,
 };
 
+/// Moves AI controlled creatures according to what they have seen.
+///
+/// Sight comes from `Perspective` on the previous dispatch, so creatures react to the world as it
+/// was when their turn came.
 #[derive(Default)]
-pub struct AI {
-    pub last_tick: u64,
-}
+pub struct AI {}
 
 impl<'a> System<'a> for AI {
     type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, WorldPosition>,
+        ReadExpect<'a, SpatialIndex>,
         ReadStorage<'a, AIControlled>,
         ReadStorage<'a, Factioned>,
-        ReadStorage<'a, WorldPosition>,
+        ReadStorage<'a, Collidable>,
+        ReadStorage<'a, Level>,
+        ReadStorage<'a, Sighted>,
+        ReadStorage<'a, Damageable>,
+        WriteStorage<'a, Behaviour>,
         WriteStorage<'a, Movable>,
-        Read<'a, WorldTime>,
+        WriteStorage<'a, Initiative>,
+        Read<'a, TileMap>,
+        Write<'a, Navigation>,
+        Write<'a, Randomness>,
     );
 
     fn run(
         &mut self,
-        (ai_controlled, factioned, world_position, mut movable, world_time): Self::SystemData,
+        (
+            entities,
+            world_position,
+            spatial_index,
+            ai_controlled,
+            factioned,
+            collidable,
+            level,
+            sighted,
+            damageable,
+            mut behaviour,
+            mut movable,
+            mut initiative,
+            tile_map,
+            mut navigation,
+            mut randomness,
+        ): Self::SystemData,
     ) {
-        if self.last_tick >= world_time.tick {
-            return;
+        for (level_entity, level) in (&entities, &level).join() {
+            if level.active && level.generated && !navigation.is_current(level_entity) {
+                // Creatures come and go, only what can't move is part of the terrain
+                let obstacles = Obstacles::from_terrain(&tile_map, &spatial_index, |entity| {
+                    collidable.contains(entity) && !movable.contains(entity)
+                });
+                navigation.reset(level_entity, obstacles);
+            }
         }
 
-        let first_player_position = (&factioned, &world_position)
+        let player = (&entities, &factioned, &world_position)
             .join()
-            .filter_map(|(factioned, world_position)| match factioned.faction {
-                Faction::Player => Some(world_position),
-                _ => None,
-            })
-            .next();
-
-        if let Some(player_position) = first_player_position {
-            for (factioned, world_position, _, movable) in
-                (&factioned, &world_position, &ai_controlled, &mut movable).join()
-            {
-                if factioned.faction == Faction::Enemy {
-                    let delta_x = (player_position.x as f64) - (world_position.x as f64);
-                    let delta_y = (player_position.y as f64) - (world_position.y as f64);
-                    let rads = (delta_y).atan2(delta_x);
-                    movable.unprocessed_move = Direction::from_radians(rads).into();
+            .find(|(_, factioned, _)| factioned.faction == Faction::Player)
+            .map(|(entity, _, world_position)| (entity, *world_position));
+
+        let is_occupied =
+            |world_position: &WorldPosition| spatial_index.any_at(*world_position, &collidable);
+
+        for (_, factioned, world_position, behaviour, initiative, movable, sighted, damageable) in (
+            &ai_controlled,
+            &factioned,
+            &world_position,
+            &mut behaviour,
+            &mut initiative,
+            (&mut movable).maybe(),
+            (&sighted).maybe(),
+            (&damageable).maybe(),
+        )
+            .join()
+        {
+            if !initiative.is_ready() {
+                continue;
+            }
+
+            let seen_player_position = player
+                .filter(|(player, _)| sighted.is_some_and(|s| s.seen.contains(player.id())))
+                .map(|(_, player_position)| player_position);
+            behaviour.state = if factioned.faction == Faction::Enemy {
+                AI::next_state(behaviour, seen_player_position, damageable)
+            } else {
+                AIState::Wandering
+            };
+
+            let direction = match behaviour.state {
+                AIState::Idle => None,
+                AIState::Wandering => AI::wander(
+                    &navigation,
+                    &mut randomness.ai,
+                    *world_position,
+                    &is_occupied,
+                ),
+                AIState::Hunting {
+                    last_known_position,
+                } => navigation
+                    .steps_towards_player(last_known_position, *world_position)
+                    .into_iter()
+                    .find(|(_, next)| *next == last_known_position || !is_occupied(next))
+                    .map(|(direction, _)| direction),
+                AIState::Searching {
+                    last_known_position,
+                } => {
+                    let step = navigation
+                        .obstacles()
+                        .and_then(|obstacles| {
+                            a_star(obstacles, *world_position, last_known_position)
+                        })
+                        .and_then(|path| path.first().copied())
+                        .filter(|next| !is_occupied(next));
+                    if step.is_none() || step == Some(last_known_position) {
+                        // Giving up once there, or when there is no way to get there
+                        behaviour.state = AIState::Wandering;
+                    }
+                    step.and_then(|next| AI::direction_to(*world_position, next))
                 }
+                AIState::Fleeing => seen_player_position.and_then(|player_position| {
+                    navigation
+                        .steps_away_from_player(player_position, *world_position)
+                        .into_iter()
+                        .find(|(_, next)| !is_occupied(next))
+                        .map(|(direction, _)| direction)
+                }),
+            };
+
+            match (direction, movable) {
+                (Some(direction), Some(movable)) => movable.unprocessed_move = direction.into(),
+                _ => initiative.spend(Action::Wait),
             }
         }
+    }
+}
+
+impl AI {
+    fn next_state(
+        behaviour: &Behaviour,
+        seen_player_position: Option<WorldPosition>,
+        damageable: Option<&Damageable>,
+    ) -> AIState {
+        match (seen_player_position, behaviour.state) {
+            (Some(_), _) if AI::is_weak(behaviour, damageable) => AIState::Fleeing,
+            (Some(last_known_position), _) => AIState::Hunting {
+                last_known_position,
+            },
+            (
+                None,
+                AIState::Hunting {
+                    last_known_position,
+                },
+            ) => AIState::Searching {
+                last_known_position,
+            },
+            (None, AIState::Fleeing) => AIState::Wandering,
+            (None, state) => state,
+        }
+    }
+
+    fn is_weak(behaviour: &Behaviour, damageable: Option<&Damageable>) -> bool {
+        damageable.is_some_and(|damageable| {
+            damageable.health * 100 < damageable.max_health * behaviour.flee_below_percent
+        })
+    }
+
+    fn wander(
+        navigation: &Navigation,
+        rng: &mut RandomGenerator,
+        world_position: WorldPosition,
+        is_occupied: &impl Fn(&WorldPosition) -> bool,
+    ) -> Option<Direction> {
+        let directions: Vec<Direction> = navigation
+            .obstacles()?
+            .neighbours(world_position)
+            .filter(|(_, next)| !is_occupied(next))
+            .map(|(direction, _)| direction)
+            .collect();
+        rng.random_in_vec(&directions).copied()
+    }
+
+    fn direction_to(from: WorldPosition, to: WorldPosition) -> Option<Direction> {
+        match (to.x as i64 - from.x as i64, to.y as i64 - from.y as i64) {
+            (1, 0) => Direction::Right.into(),
+            (-1, 0) => Direction::Left.into(),
+            (0, -1) => Direction::Up.into(),
+            (0, 1) => Direction::Down.into(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::game::{
+        common::UIEvent,
+        components::{behaviour::AIState, damageable::Damageable},
+        test_harness::TestWorld,
+        world::WorldPosition,
+    };
+
+    #[test]
+    fn monsters_hunt_the_player_around_walls() {
+        let mut test_world = TestWorld::new(10, 5);
+        let player = test_world.player(0, 2);
+        for y in 0..4 {
+            test_world.wall(2, y);
+        }
+        let monster = test_world.monster(4, 2, 10, 1);
+        test_world.set_state(
+            monster,
+            AIState::Hunting {
+                last_known_position: WorldPosition { x: 0, y: 2 },
+            },
+        );
+
+        // Bumping into the edge of the world keeps the player in place while time passes
+        for _ in 0..7 {
+            test_world.press(UIEvent::Left);
+        }
+        let WorldPosition { x, y } = test_world.position(monster).unwrap();
+        assert_eq!(x.abs_diff(0) + y.abs_diff(2), 1);
+
+        test_world.press(UIEvent::Left);
+        assert_eq!(test_world.health(player), Some(99));
+    }
+
+    #[test]
+    fn monsters_do_not_notice_the_player_through_walls() {
+        let mut test_world = TestWorld::new(10, 5);
+        test_world.player(0, 2);
+        for y in 0..5 {
+            test_world.wall(2, y);
+        }
+        let monster = test_world.monster(4, 2, 10, 1);
+
+        for _ in 0..3 {
+            test_world.press(UIEvent::Left);
+        }
+        assert_eq!(test_world.state(monster), Some(AIState::Idle));
+        assert_eq!(
+            test_world.position(monster),
+            Some(WorldPosition { x: 4, y: 2 })
+        );
+    }
+
+    #[test]
+    fn monsters_search_where_the_player_was_last_seen() {
+        let mut test_world = TestWorld::new(10, 5);
+        test_world.player(0, 2);
+        for y in 0..5 {
+            test_world.wall(2, y);
+        }
+        let monster = test_world.monster(6, 2, 10, 1);
+        test_world.set_state(
+            monster,
+            AIState::Hunting {
+                last_known_position: WorldPosition { x: 3, y: 2 },
+            },
+        );
+
+        test_world.press(UIEvent::Left);
+        assert_eq!(
+            test_world.state(monster),
+            Some(AIState::Searching {
+                last_known_position: WorldPosition { x: 3, y: 2 }
+            })
+        );
+        test_world.press(UIEvent::Left);
+        test_world.press(UIEvent::Left);
+        assert_eq!(
+            test_world.position(monster),
+            Some(WorldPosition { x: 3, y: 2 })
+        );
+        assert_eq!(test_world.state(monster), Some(AIState::Wandering));
+    }
+
+    #[test]
+    fn wounded_monsters_flee() {
+        let mut test_world = TestWorld::new(10, 1);
+        test_world.player(0, 0);
+        let monster = test_world.monster(4, 0, 10, 1);
+        test_world.simulation.tick(vec![]);
+
+        test_world
+            .simulation
+            .world
+            .write_storage::<Damageable>()
+            .get_mut(monster)
+            .unwrap()
+            .health = 2;
+        test_world.press(UIEvent::Left);
+        test_world.press(UIEvent::Left);
 
-        self.last_tick = world_time.tick;
+        assert_eq!(test_world.state(monster), Some(AIState::Fleeing));
+        assert_eq!(
+            test_world.position(monster),
+            Some(WorldPosition { x: 5, y: 0 })
+        );
     }
 }
@@ -1,86 +1,324 @@
-use std::ops::Not;
-
-use log::info;
+use shrev::EventChannel;
 use specs::prelude::*;
 
 use crate::game::{
-    components::{climbable::Climbable, level::Level, player_controlled::PlayerControlled, parent::Parent, sighted::{Sighted, self}},
-    world::{WorldPosition, WorldPositionLookupTable},
+    algorithms::pathfinding::{DijkstraMap, Obstacles},
+    components::{
+        climbable::Climbable,
+        collidable::Collidable,
+        initiative::{Action, Initiative},
+        level::Level,
+        parent::Parent,
+        player_controlled::PlayerControlled,
+        sighted::Sighted,
+        stowed::Stowed,
+    },
+    events::GameEvent,
+    spatial_index::{SpatialData, SpatialIndex},
+    tile_map::TileMap,
+    world::WorldPosition,
 };
 
+/// Takes the player up or down the stairs they stand on.
+///
+/// Levels are kept once left: their entities are stowed away, and put back where they were when
+/// the player returns. A level is only created the first time the player goes down to it.
 pub struct Climbing {}
 
+impl Climbing {
+    /// The closest free cell around the stairs the player arrives by, so that they don't climb
+    /// straight back.
+    fn arrival_position(
+        tile_map: &TileMap,
+        spatial_index: &SpatialIndex,
+        collidable: &ReadStorage<Collidable>,
+        climbable: &ReadStorage<Climbable>,
+        stairs: WorldPosition,
+    ) -> WorldPosition {
+        let obstacles = Obstacles::from_terrain(tile_map, spatial_index, |entity| {
+            collidable.contains(entity)
+        });
+        let distances = DijkstraMap::new(&obstacles, &[stairs]);
+        tile_map
+            .positions()
+            .filter(|position| !spatial_index.any_at(*position, climbable))
+            .filter_map(|position| distances.distance(position).map(|d| (d, position)))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, position)| position)
+            .unwrap_or(stairs)
+    }
+}
+
 impl<'a> System<'a> for Climbing {
     type SystemData = (
-        Entities<'a>,
+        SpatialData<'a>,
         ReadStorage<'a, PlayerControlled>,
+        ReadStorage<'a, Collidable>,
         ReadStorage<'a, Climbable>,
-        WriteStorage<'a, WorldPosition>,
+        WriteStorage<'a, Stowed>,
         WriteStorage<'a, Level>,
         WriteStorage<'a, Parent>,
         WriteStorage<'a, Sighted>,
-        Write<'a, WorldPositionLookupTable>,
+        WriteStorage<'a, Initiative>,
+        Write<'a, TileMap>,
+        Write<'a, EventChannel<GameEvent>>,
     );
 
     fn run(
         &mut self,
         (
-            entities,
+            mut spatial_data,
             player_controlled,
+            collidable,
             climbable,
-            mut world_position,
+            mut stowed,
             mut level,
             mut parent,
             mut sighted,
-            mut world_position_lookup_table,
+            mut initiative,
+            mut tile_map,
+            mut events,
         ): Self::SystemData,
     ) {
-        let mut next_level = false;
-        for (_, world_position) in (&player_controlled, &world_position).join() {
-            if let Some(entities) = world_position_lookup_table
-                .world_position_entities
-                .get(world_position)
-            {
-                for entity in entities {
-                    if climbable.get(*entity).is_some() {
-                        next_level = true;
-                    }
-                }
+        let Some((player, player_position)) = (
+            &spatial_data.entities,
+            &player_controlled,
+            &spatial_data.world_position,
+        )
+            .join()
+            .map(|(entity, _, world_position)| (entity, *world_position))
+            .next()
+        else {
+            return;
+        };
+        let Some((_, direction)) = spatial_data.index().find_at(player_position, &climbable) else {
+            return;
+        };
+        let Some((old_level, old_depth, root)) = (&spatial_data.entities, &level, &parent)
+            .join()
+            .find(|(_, level, _)| level.active)
+            .map(|(entity, level, parent)| (entity, level.depth, parent.entity))
+        else {
+            return;
+        };
+        let direction = *direction;
+        let depth = match direction {
+            Climbable::Down => old_depth + 1,
+            Climbable::Up if old_depth > 1 => old_depth - 1,
+            Climbable::Up => {
+                events.single_write(GameEvent::NoWayOut { entity: player });
+                return;
             }
+        };
+        events.single_write(GameEvent::Climbed {
+            entity: player,
+            direction,
+            depth,
+        });
+
+        if let Some(initiative) = initiative.get_mut(player) {
+            initiative.spend(Action::Climb);
         }
 
-        if next_level {
+        // The player keeps the memory of what they saw, so explored levels stay explored
+        if let Some(sighted) = sighted.get_mut(player) {
+            sighted.seen.clear();
+        }
+        // Fields of view were cast over the terrain being swapped out
+        for sighted in (&mut sighted).join() {
+            sighted.invalidate();
+        }
+        spatial_data.world_position.remove(player);
 
-            let mut old_level: Option<(Entity, &mut Parent)> = None;
-            for (entity, _, parent) in (&entities, &level, &mut parent).join() {
-                old_level = (entity, parent).into();
+        let left = level.get_mut(old_level).unwrap();
+        left.active = false;
+        left.tile_map = Some(std::mem::take(&mut *tile_map));
+        for (entity, _) in (&spatial_data.entities, &parent)
+            .join()
+            .filter(|(_, parent)| parent.entity == old_level)
+        {
+            if let Some(position) = spatial_data.world_position.remove(entity) {
+                stowed
+                    .insert(
+                        entity,
+                        Stowed {
+                            world_position: position,
+                        },
+                    )
+                    .unwrap();
             }
-            
-            if let Some((old_level_entity, old_entity_parent)) = old_level {
-
-                entities.build_entity()
-                    .with(Level::default(), &mut level)
-                    .with(Parent { entity: old_entity_parent.entity }, &mut parent)
-                    .build();
-                
-                entities.delete(old_level_entity).unwrap();
-
-                for (entity, sighted, _) in (&entities, &mut sighted, &player_controlled).join() {
-                    sighted.seen.clear();
-                    sighted.seen_recently.clear();
-                    world_position.remove(entity);
-                }
-                
-                for (entity, entity_parent) in (&entities, &parent).join() {
-                    if entity_parent.entity == old_level_entity {
-                        entities.delete(entity).unwrap();
-                        world_position_lookup_table.remove(entity);
-                    }
-                }
+        }
+
+        let visited = (&spatial_data.entities, &mut level)
+            .join()
+            .find(|(_, level)| level.depth == depth);
+        let Some((new_level, visited)) = visited else {
+            spatial_data
+                .entities
+                .build_entity()
+                .with(Level::new(depth), &mut level)
+                .with(Parent { entity: root }, &mut parent)
+                .build();
+            return;
+        };
+        visited.active = true;
+        *tile_map = visited.tile_map.take().unwrap_or_default();
+
+        let returning: Vec<Entity> = (&spatial_data.entities, &parent, &stowed)
+            .join()
+            .filter(|(_, parent, _)| parent.entity == new_level)
+            .map(|(entity, _, _)| entity)
+            .collect();
+        let mut stairs_back = player_position;
+        for entity in returning {
+            let position = stowed.remove(entity).unwrap().world_position;
+            spatial_data
+                .world_position
+                .insert(entity, position)
+                .unwrap();
+            if climbable.get(entity).is_some_and(|c| *c != direction) {
+                stairs_back = position;
             }
+        }
+        let position = Climbing::arrival_position(
+            &tile_map,
+            spatial_data.index(),
+            &collidable,
+            &climbable,
+            stairs_back,
+        );
+        spatial_data
+            .world_position
+            .insert(player, position)
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::game::{
+        common::UIEvent,
+        components::{level::Level, player_controlled::PlayerControlled},
+        test_harness::TestWorld,
+        tile_map::{Tile, TileMap},
+        world::WorldPosition,
+    };
+
+    fn depth(test_world: &TestWorld, level: Entity) -> Option<u32> {
+        test_world
+            .simulation
+            .world
+            .read_storage::<Level>()
+            .get(level)
+            .map(|level| level.depth)
+    }
+
+    #[test]
+    fn stepping_on_stairs_generates_a_new_level() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let stairs = test_world.stairs(6, 5);
+        let gold = test_world.gold(1, 1);
+        let old_level = test_world.level();
+
+        test_world.press(UIEvent::Right);
+
+        // The old level is stowed away rather than deleted
+        assert!(test_world.is_alive(old_level));
+        assert!(test_world.is_alive(stairs));
+        assert!(test_world.is_alive(gold));
+        assert_eq!(test_world.position(gold), None);
+        assert!(test_world.entities_at(1, 1).is_empty());
+        assert!(test_world.is_alive(player));
+        assert_eq!(test_world.position(player), None);
+
+        let new_level = test_world.level();
+        assert_ne!(new_level, old_level);
+        assert_eq!(depth(&test_world, new_level), Some(2));
+        assert_eq!(
+            test_world.parent(new_level),
+            Some(test_world.simulation.root)
+        );
+
+        test_world.send(vec![]);
+
+        let world = &test_world.simulation.world;
+        assert!(
+            world
+                .read_storage::<Level>()
+                .get(new_level)
+                .unwrap()
+                .generated
+        );
+        assert_eq!(world.read_storage::<PlayerControlled>().join().count(), 1);
+        assert!(test_world.position(player).is_some());
+    }
 
-            world_position_lookup_table.clear();
+    #[test]
+    fn climbing_back_up_returns_to_the_level_as_it_was_left() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let stairs = test_world.stairs(6, 5);
+        let gold = test_world.gold(1, 1);
+        let first_level = test_world.level();
+
+        test_world.press(UIEvent::Right);
+        let second_level = test_world.level();
+        for level in (&mut test_world.simulation.world.write_storage::<Level>()).join() {
+            level.generated = true;
         }
+        test_world
+            .simulation
+            .world
+            .insert(TileMap::new(10, 10, Tile::Floor));
+        let up_stairs = test_world.up_stairs(2, 2);
+        test_world.teleport(player, 2, 2);
+        test_world.send(vec![]);
+
+        assert_eq!(test_world.level(), first_level);
+        assert_eq!(depth(&test_world, first_level), Some(1));
+        assert_eq!(
+            test_world.position(gold),
+            Some(WorldPosition { x: 1, y: 1 })
+        );
+        assert_eq!(
+            test_world.position(stairs),
+            Some(WorldPosition { x: 6, y: 5 })
+        );
+        assert_eq!(test_world.position(up_stairs), None);
+        assert_eq!(test_world.parent(up_stairs), Some(second_level));
+
+        // Next to the stairs, rather than on them
+        let position = test_world.position(player).unwrap();
+        assert_eq!(position.x.abs_diff(6) + position.y.abs_diff(5), 1);
+
+        // Going down again leads back to the same second level
+        test_world.teleport(player, 6, 5);
+        test_world.send(vec![]);
+        assert_eq!(test_world.level(), second_level);
+        assert_eq!(
+            test_world.position(up_stairs),
+            Some(WorldPosition { x: 2, y: 2 })
+        );
+        let position = test_world.position(player).unwrap();
+        assert_eq!(position.x.abs_diff(2) + position.y.abs_diff(2), 1);
+    }
+
+    #[test]
+    fn there_is_no_way_up_from_the_first_floor() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.up_stairs(6, 5);
+        let level = test_world.level();
+
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(test_world.level(), level);
+        assert_eq!(
+            test_world.position(player),
+            Some(WorldPosition { x: 6, y: 5 })
+        );
     }
 }
- 
\ No newline at end of file
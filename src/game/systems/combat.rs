@@ -1,38 +1,282 @@
-use log::info;
+use shrev::EventChannel;
 use specs::prelude::*;
 
 use crate::game::{
-    components::{armed::Armed, damageable::Damageable, factioned::Factioned},
-    world::WorldPositionLookupTable,
+    components::{
+        armed::Armed,
+        damageable::Damageable,
+        equippable::Equippable,
+        equipped::Equipped,
+        initiative::{Action, Initiative},
+        parent::Parent,
+        status_effects::StatusEffects,
+    },
+    events::GameEvent,
+    random::Randomness,
 };
 
+/// Chance of an attack to hit when the attacker's accuracy and the target's defense are even.
+const BASE_HIT_CHANCE: f64 = 0.7;
+/// How much each point of accuracy over the target's defense adds to the chance to hit.
+const HIT_CHANCE_PER_POINT: f64 = 0.05;
+/// Even the best defended targets get hit now and then.
+const MIN_HIT_CHANCE: f64 = 0.05;
+const CRITICAL_MULTIPLIER: u32 = 2;
+
+pub fn hit_chance(accuracy: u32, defense: u32) -> f64 {
+    let difference = accuracy as f64 - defense as f64;
+    (BASE_HIT_CHANCE + HIT_CHANCE_PER_POINT * difference).clamp(MIN_HIT_CHANCE, 1.0)
+}
+
+/// Resolves the attacks of armed entities against their targets.
+///
+/// Every attack rolls to hit and then for a critical hit on the combat stream, the damage that
+/// gets through depending on the target's resistances and armour. Targets surviving a hit come
+/// under the effect the attacker inflicts, if any.
 pub struct Combat {}
 
+#[derive(SystemData)]
+pub struct CombatData<'a> {
+    entities: Entities<'a>,
+    armed: WriteStorage<'a, Armed>,
+    damageable: WriteStorage<'a, Damageable>,
+    initiative: WriteStorage<'a, Initiative>,
+    parent: ReadStorage<'a, Parent>,
+    equippable: ReadStorage<'a, Equippable>,
+    equipped: ReadStorage<'a, Equipped>,
+    status_effects: WriteStorage<'a, StatusEffects>,
+    randomness: Write<'a, Randomness>,
+    events: Write<'a, EventChannel<GameEvent>>,
+}
+
+impl Combat {
+    /// The damage and armour the items equipped by `owner` add.
+    fn equipment_bonus(data: &CombatData, owner: Entity) -> (u32, u32) {
+        (&data.parent, &data.equippable, &data.equipped)
+            .join()
+            .filter(|(parent, _, _)| parent.entity == owner)
+            .fold((0, 0), |(damage, armor), (_, equippable, _)| {
+                (damage + equippable.damage, armor + equippable.armor)
+            })
+    }
+}
+
 impl<'a> System<'a> for Combat {
-    type SystemData = (
-        Entities<'a>,
-        Write<'a, WorldPositionLookupTable>,
-        WriteStorage<'a, Armed>,
-        WriteStorage<'a, Damageable>,
-    );
-
-    fn run(
-        &mut self,
-        (entities, mut world_position_lookup_table, mut armed, mut damageable): Self::SystemData,
-    ) {
-        for (armed_entity, armed) in (&entities, &mut armed).join() {
-            if let Some(target) = armed.targetting.take() {
-                if target == armed_entity {
-                    continue;
-                }
-                if let Some(damage) = damageable.get_mut(target) {
-                    damage.health = damage.health.checked_sub(armed.damage).unwrap_or(0);
-                    if damage.health == 0 {
-                        entities.delete(target).unwrap();
-                        world_position_lookup_table.remove(target);
+    type SystemData = CombatData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let attacks: Vec<(Entity, Entity)> = (&data.entities, &mut data.armed)
+            .join()
+            .filter_map(|(attacker, armed)| Some((attacker, armed.targetting.take()?)))
+            .filter(|(attacker, target)| attacker != target)
+            .collect();
+
+        for (attacker, target) in attacks {
+            if let Some(initiative) = data.initiative.get_mut(attacker) {
+                initiative.spend(Action::Attack);
+            }
+            let (Some(armed), Some(damageable)) =
+                (data.armed.get(attacker), data.damageable.get(target))
+            else {
+                continue;
+            };
+            // Already dying from an earlier attack
+            if damageable.health == 0 {
+                continue;
+            }
+            let (damage, damage_type, accuracy, critical_chance, inflicts) = (
+                armed.damage,
+                armed.damage_type,
+                armed.accuracy,
+                armed.critical_chance,
+                armed.inflicts,
+            );
+
+            let chance = hit_chance(accuracy, damageable.defense);
+            if data.randomness.combat.next_f64() >= chance {
+                data.events
+                    .single_write(GameEvent::Missed { attacker, target });
+                continue;
+            }
+            let critical = data.randomness.combat.next_f64() < critical_chance;
+
+            let (damage_bonus, _) = Combat::equipment_bonus(&data, attacker);
+            let (_, armor_bonus) = Combat::equipment_bonus(&data, target);
+            let mut damage = damage + damage_bonus;
+            if critical {
+                damage *= CRITICAL_MULTIPLIER;
+            }
+            if let Some(damageable) = data.damageable.get_mut(target) {
+                let damage = damageable.damage_taken(damage, damage_type, armor_bonus);
+                damageable.health = damageable.health.saturating_sub(damage);
+                data.events.single_write(GameEvent::Hit {
+                    attacker,
+                    target,
+                    damage,
+                    damage_type,
+                    critical,
+                });
+                if damageable.health == 0 {
+                    data.events.single_write(GameEvent::Died {
+                        entity: target,
+                        killer: Some(attacker),
+                    });
+                } else if let Some(effect) = inflicts {
+                    if StatusEffects::afflict(&mut data.status_effects, target, effect) {
+                        data.events.single_write(GameEvent::Afflicted {
+                            entity: target,
+                            kind: effect.kind,
+                        });
                     }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        common::UIEvent,
+        components::{armed::DamageType, damageable::Damageable},
+        test_harness::{TestWorld, SURE_HIT_ACCURACY},
+        tile_map::Tile,
+        world::WorldPosition,
+    };
+
+    use super::{hit_chance, MIN_HIT_CHANCE};
+
+    #[test]
+    fn accuracy_and_defense_decide_the_chance_to_hit() {
+        assert_eq!(hit_chance(3, 3), 0.7);
+        assert!(hit_chance(5, 3) > hit_chance(3, 3));
+        assert!(hit_chance(3, 5) < hit_chance(3, 3));
+        assert_eq!(hit_chance(SURE_HIT_ACCURACY, 0), 1.0);
+        assert_eq!(hit_chance(0, 100), MIN_HIT_CHANCE);
+    }
+
+    #[test]
+    fn resistances_and_armor_lower_the_damage_taken() {
+        let damageable = Damageable {
+            armor: 2,
+            resistances: vec![DamageType::Fire],
+            vulnerabilities: vec![DamageType::Cold],
+            ..Damageable::new(10)
+        };
+
+        assert_eq!(damageable.damage_taken(5, DamageType::Physical, 0), 3);
+        assert_eq!(damageable.damage_taken(5, DamageType::Physical, 1), 2);
+        assert_eq!(damageable.damage_taken(5, DamageType::Fire, 0), 2);
+        assert_eq!(damageable.damage_taken(5, DamageType::Cold, 0), 10);
+        assert_eq!(damageable.damage_taken(5, DamageType::Poison, 0), 5);
+        assert_eq!(damageable.damage_taken(1, DamageType::Physical, 0), 1);
+    }
+
+    #[test]
+    fn well_defended_targets_are_mostly_missed() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let monster = test_world.monster(6, 5, 100, 0);
+        test_world.set_attack(player, 0, 0.0);
+        test_world.set_defenses(
+            monster,
+            Damageable {
+                defense: 100,
+                ..Damageable::new(100)
+            },
+        );
+
+        for _ in 0..10 {
+            test_world.press(UIEvent::Right);
+        }
+
+        assert!(test_world.health(monster) > Some(90));
+    }
+
+    #[test]
+    fn critical_hits_deal_double_damage() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let monster = test_world.monster(6, 5, 20, 1);
+        test_world.set_attack(player, SURE_HIT_ACCURACY, 1.0);
+
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(test_world.health(monster), Some(10));
+    }
+
+    #[test]
+    fn bumping_into_a_monster_attacks_it() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let monster = test_world.monster(6, 5, 10, 1);
+
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(test_world.health(monster), Some(5));
+        assert_eq!(
+            test_world.position(player),
+            Some(WorldPosition { x: 5, y: 5 })
+        );
+
+        // The monster only notices the player at the end of the turn, and strikes back on the next
+        test_world.send(vec![]);
+        assert_eq!(test_world.health(player), Some(99));
+    }
+
+    #[test]
+    fn monsters_die_when_out_of_health() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let monster = test_world.monster(6, 5, 10, 1);
+
+        test_world.press(UIEvent::Right);
+        test_world.press(UIEvent::Right);
+
+        assert!(!test_world.is_alive(monster));
+        let remains = test_world.entities_at(6, 5);
+        assert_eq!(remains.len(), 1);
+        assert_eq!(
+            test_world.description(remains[0]).as_deref(),
+            Some("Monster Corpse")
+        );
+
+        test_world.press(UIEvent::Right);
+        assert_eq!(
+            test_world.position(player),
+            Some(WorldPosition { x: 6, y: 5 })
+        );
+    }
+
+    #[test]
+    fn walls_are_not_attacked() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.wall(6, 5);
+
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(test_world.tile(6, 5), Some(Tile::StoneWall));
+        assert_eq!(
+            test_world.position(player),
+            Some(WorldPosition { x: 5, y: 5 })
+        );
+    }
+
+    #[test]
+    fn equipped_weapons_add_to_the_damage() {
+        let mut test_world = TestWorld::new(10, 10);
+        test_world.player(4, 5);
+        let weapon = test_world.weapon(5, 5, 3);
+        let monster = test_world.monster(6, 5, 20, 1);
+        test_world.press(UIEvent::Right);
+        // Picking up the weapon took some time, the command waits for the player's turn
+        test_world.press(UIEvent::EquipItem(0));
+        test_world.send(vec![]);
+        assert!(test_world.is_equipped(weapon));
+
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(test_world.health(monster), Some(12));
+    }
+}
@@ -0,0 +1,204 @@
+use shrev::EventChannel;
+use specs::prelude::*;
+
+use crate::game::{
+    components::{
+        equipped::Equipped,
+        inventoried::Inventoried,
+        level::Level,
+        parent::Parent,
+        status_effects::{StatusEffectKind, StatusEffects},
+    },
+    events::GameEvent,
+    spawner::Spawner,
+    world::{GameOver, PlayerDeath, WorldTime},
+};
+
+/// Turns the entities that died into corpses, leaving what they carried on the floor.
+///
+/// The game is over when the player dies: the cause of death is kept in `GameOver`.
+pub struct Death {
+    reader_id: ReaderId<GameEvent>,
+}
+
+impl Death {
+    pub fn new(world: &mut World) -> Death {
+        Death {
+            reader_id: world
+                .entry::<EventChannel<GameEvent>>()
+                .or_insert_with(EventChannel::default)
+                .register_reader(),
+        }
+    }
+}
+
+#[derive(SystemData)]
+pub struct DeathData<'a> {
+    spawner: Spawner<'a>,
+    events: Read<'a, EventChannel<GameEvent>>,
+    level: ReadStorage<'a, Level>,
+    equipped: WriteStorage<'a, Equipped>,
+    status_effects: ReadStorage<'a, StatusEffects>,
+    world_time: Read<'a, WorldTime>,
+    game_over: Write<'a, GameOver>,
+}
+
+impl<'a> System<'a> for Death {
+    type SystemData = DeathData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let deaths: Vec<(Entity, Option<Entity>)> = data
+            .events
+            .read(&mut self.reader_id)
+            .filter_map(|event| match event {
+                GameEvent::Died { entity, killer } => Some((*entity, *killer)),
+                _ => None,
+            })
+            .collect();
+
+        // Only entities on the active level fight, so that is where they die
+        let Some((level, depth)) = (&data.spawner.entities, &data.level)
+            .join()
+            .find(|(_, level)| level.active)
+            .map(|(entity, level)| (entity, level.depth))
+        else {
+            return;
+        };
+
+        for (entity, killer) in deaths {
+            let spawner = &mut data.spawner;
+            if !spawner.entities.is_alive(entity) {
+                continue;
+            }
+            let name = spawner
+                .describable
+                .get(entity)
+                .map(|describable| describable.description.clone())
+                .unwrap_or_else(|| "Something".to_owned());
+
+            if let Some(position) = spawner.world_position.get(entity).copied() {
+                if let Some(corpse) = spawner.spawn("corpse", position, Some(level)) {
+                    if let Some(describable) = spawner.describable.get_mut(corpse) {
+                        describable.description = format!("{} Corpse", name);
+                    }
+                    let color = spawner
+                        .render
+                        .get(entity)
+                        .map(|render| render.foreground_color);
+                    if let (Some(render), Some(color)) = (spawner.render.get_mut(corpse), color) {
+                        render.foreground_color = color;
+                    }
+                }
+
+                let items = Inventoried::items(
+                    entity,
+                    &spawner.entities,
+                    &spawner.parent,
+                    &spawner.pickupable,
+                );
+                for item in items {
+                    data.equipped.remove(item);
+                    spawner
+                        .parent
+                        .insert(item, Parent { entity: level })
+                        .unwrap();
+                    spawner.world_position.insert(item, position).unwrap();
+                }
+            }
+
+            if spawner.player_controlled.contains(entity) {
+                let killer = killer
+                    .and_then(|killer| spawner.describable.get(killer))
+                    .map(|describable| describable.description.clone());
+                let poisoned = data
+                    .status_effects
+                    .get(entity)
+                    .is_some_and(|status_effects| status_effects.has(StatusEffectKind::Poison));
+                let cause = match killer {
+                    Some(killer) => format!("Killed by a {}", killer),
+                    None if poisoned => "Died of poison".to_owned(),
+                    None => "Died".to_owned(),
+                };
+                data.game_over.player_death = Some(PlayerDeath {
+                    cause,
+                    depth,
+                    tick: data.world_time.tick,
+                });
+            }
+
+            spawner.entities.delete(entity).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::WorldExt;
+
+    use crate::game::{
+        common::UIEvent,
+        components::damageable::Damageable,
+        test_harness::TestWorld,
+        world::{PlayerDeath, WorldTime},
+    };
+
+    #[test]
+    fn killed_monsters_leave_a_corpse_and_their_items() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let monster = test_world.monster(6, 5, 5, 1);
+        let gold = test_world.gold(1, 1);
+        test_world.give(gold, monster);
+
+        test_world.press(UIEvent::Right);
+        test_world.send(vec![]);
+
+        assert!(!test_world.is_alive(monster));
+        assert_eq!(test_world.parent(gold), Some(test_world.level()));
+        let remains = test_world.entities_at(6, 5);
+        assert_eq!(remains.len(), 2);
+        assert!(remains.contains(&gold));
+
+        test_world.press(UIEvent::Right);
+        test_world.send(vec![]);
+        assert_eq!(test_world.parent(gold), Some(player));
+    }
+
+    #[test]
+    fn the_game_is_over_when_the_player_dies() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.set_defenses(player, Damageable::new(1));
+        test_world.monster(6, 5, 100, 1);
+
+        test_world.press(UIEvent::Right);
+        test_world.send(vec![]);
+
+        assert!(!test_world.is_alive(player));
+        let tick = test_world
+            .simulation
+            .world
+            .read_resource::<WorldTime>()
+            .tick;
+        assert_eq!(
+            test_world.player_death(),
+            Some(PlayerDeath {
+                cause: "Killed by a Monster".to_owned(),
+                depth: 1,
+                tick,
+            })
+        );
+
+        // Time stands still from then on
+        test_world.send(vec![]);
+        test_world.send(vec![]);
+        assert_eq!(
+            test_world
+                .simulation
+                .world
+                .read_resource::<WorldTime>()
+                .tick,
+            tick
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use shrev::EventChannel;
+use specs::prelude::*;
+
+use crate::game::{
+    components::{
+        armed::DamageType,
+        damageable::Damageable,
+        status_effects::{StatusEffectKind, StatusEffects},
+    },
+    events::GameEvent,
+    world::{WorldPosition, WorldTime},
+};
+
+/// Wears status effects off by one turn for every tick the world advanced, poisoning and healing
+/// the entities under them along the way.
+///
+/// The other effects are looked up where they matter: haste in `Scheduling`, blindness in
+/// `Perspective` and confusion in `Movement`. Like turns, effects only wear off on the player's
+/// level, time stands still for the stowed entities.
+pub struct Effects {}
+
+#[derive(SystemData)]
+pub struct EffectsData<'a> {
+    entities: Entities<'a>,
+    status_effects: WriteStorage<'a, StatusEffects>,
+    damageable: WriteStorage<'a, Damageable>,
+    world_position: ReadStorage<'a, WorldPosition>,
+    world_time: Read<'a, WorldTime>,
+    events: Write<'a, EventChannel<GameEvent>>,
+}
+
+impl Effects {
+    /// Returns false once the entity died of its effects.
+    fn take_turn(
+        entity: Entity,
+        status_effects: &mut StatusEffects,
+        damageable: Option<&mut Damageable>,
+        events: &mut EventChannel<GameEvent>,
+    ) -> bool {
+        if let Some(damageable) = damageable {
+            for effect in &status_effects.effects {
+                match effect.kind {
+                    StatusEffectKind::Poison => {
+                        let damage =
+                            damageable.damage_taken(effect.strength, DamageType::Poison, 0);
+                        damageable.health = damageable.health.saturating_sub(damage);
+                        events.single_write(GameEvent::Poisoned { entity, damage });
+                    }
+                    StatusEffectKind::Regeneration => {
+                        damageable.health =
+                            (damageable.health + effect.strength).min(damageable.max_health);
+                    }
+                    _ => {}
+                }
+            }
+            if damageable.health == 0 {
+                events.single_write(GameEvent::Died {
+                    entity,
+                    killer: None,
+                });
+                return false;
+            }
+        }
+
+        for effect in &mut status_effects.effects {
+            effect.turns = effect.turns.saturating_sub(1);
+            if effect.turns == 0 {
+                events.single_write(GameEvent::Recovered {
+                    entity,
+                    kind: effect.kind,
+                });
+            }
+        }
+        status_effects.effects.retain(|effect| effect.turns > 0);
+        true
+    }
+}
+
+impl<'a> System<'a> for Effects {
+    type SystemData = EffectsData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let tick = data.world_time.tick;
+        for (entity, status_effects, mut damageable, on_level) in (
+            &data.entities,
+            &mut data.status_effects,
+            (&mut data.damageable).maybe(),
+            (&data.world_position).maybe(),
+        )
+            .join()
+        {
+            let ticked_at = status_effects.ticked_at.replace(tick).unwrap_or(tick);
+            // Already dying from something else
+            if on_level.is_none() || damageable.as_ref().is_some_and(|d| d.health == 0) {
+                continue;
+            }
+            for _ in ticked_at..tick {
+                if !Effects::take_turn(
+                    entity,
+                    status_effects,
+                    damageable.as_deref_mut(),
+                    &mut data.events,
+                ) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::WorldExt;
+
+    use crate::game::{
+        common::UIEvent,
+        components::{
+            behaviour::AIState,
+            damageable::Damageable,
+            sighted::Sighted,
+            status_effects::{StatusEffect, StatusEffectKind},
+        },
+        test_harness::TestWorld,
+        world::{PlayerDeath, WorldPosition},
+    };
+
+    fn effect(kind: StatusEffectKind, turns: u32, strength: u32) -> StatusEffect {
+        StatusEffect {
+            kind,
+            turns,
+            strength,
+        }
+    }
+
+    #[test]
+    fn poison_hurts_every_turn_until_it_wears_off() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.afflict(player, effect(StatusEffectKind::Poison, 3, 2));
+        test_world.send(vec![]);
+
+        test_world.pass_turns(5);
+
+        assert_eq!(test_world.health(player), Some(94));
+        assert!(!test_world.is_under(player, StatusEffectKind::Poison));
+        assert_eq!(
+            test_world.messages(),
+            vec![
+                "You suffer 2 poison damage.",
+                "You suffer 2 poison damage.",
+                "You suffer 2 poison damage.",
+                "You are no longer poisoned.",
+            ]
+        );
+    }
+
+    #[test]
+    fn poison_can_kill() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.set_defenses(player, Damageable::new(2));
+        test_world.afflict(player, effect(StatusEffectKind::Poison, 10, 1));
+        test_world.send(vec![]);
+
+        test_world.pass_turns(2);
+
+        assert!(!test_world.is_alive(player));
+        assert_eq!(
+            test_world.player_death().map(|death| death.cause),
+            Some("Died of poison".to_owned())
+        );
+        assert!(matches!(
+            test_world.player_death(),
+            Some(PlayerDeath { depth: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn regeneration_heals_up_to_full_health() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.set_defenses(
+            player,
+            Damageable {
+                health: 5,
+                ..Damageable::new(10)
+            },
+        );
+        test_world.afflict(player, effect(StatusEffectKind::Regeneration, 10, 2));
+        test_world.send(vec![]);
+
+        test_world.pass_turns(1);
+        assert_eq!(test_world.health(player), Some(7));
+        test_world.pass_turns(3);
+        assert_eq!(test_world.health(player), Some(10));
+    }
+
+    #[test]
+    fn hasted_entities_act_twice_per_turn() {
+        let mut test_world = TestWorld::new(20, 3);
+        test_world.player(0, 1);
+        let monster = test_world.monster(19, 1, 10, 1);
+        test_world.afflict(monster, effect(StatusEffectKind::Haste, 10, 0));
+        test_world.set_state(
+            monster,
+            AIState::Hunting {
+                last_known_position: WorldPosition { x: 0, y: 1 },
+            },
+        );
+
+        test_world.send(vec![]);
+        test_world.send(vec![]);
+
+        assert_eq!(
+            test_world.position(monster),
+            Some(WorldPosition { x: 17, y: 1 })
+        );
+    }
+
+    #[test]
+    fn blindness_shrinks_sight_until_it_wears_off() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        let gold = test_world.gold(4, 1);
+        test_world.send(vec![]);
+        assert!(test_world.sees(player, gold));
+
+        test_world.afflict(player, effect(StatusEffectKind::Blindness, 2, 0));
+        test_world.send(vec![]);
+        assert!(!test_world.sees(player, gold));
+        assert!(test_world
+            .simulation
+            .world
+            .read_component::<Sighted>()
+            .get(player)
+            .is_some_and(|sighted| sighted.visible.len() < 10));
+
+        test_world.pass_turns(2);
+        assert!(test_world.sees(player, gold));
+    }
+
+    #[test]
+    fn confused_entities_stumble_around() {
+        let mut test_world = TestWorld::new(30, 30);
+        let player = test_world.player(15, 15);
+        test_world.afflict(player, effect(StatusEffectKind::Confusion, 100, 0));
+        test_world.send(vec![]);
+
+        for _ in 0..10 {
+            test_world.press(UIEvent::Right);
+        }
+
+        assert_ne!(
+            test_world.position(player),
+            Some(WorldPosition { x: 25, y: 15 })
+        );
+    }
+
+    #[test]
+    fn venomous_hits_poison_their_target() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let monster = test_world.monster(6, 5, 100, 1);
+        test_world.set_inflicts(monster, effect(StatusEffectKind::Poison, 3, 1));
+
+        test_world.press(UIEvent::Right);
+        test_world.send(vec![]);
+
+        assert!(test_world.is_under(player, StatusEffectKind::Poison));
+        assert!(test_world
+            .messages()
+            .contains(&"You are poisoned.".to_owned()));
+    }
+}
@@ -0,0 +1,55 @@
+use specs::prelude::*;
+
+use crate::game::{
+    camera::Camera, components::player_controlled::PlayerControlled, tile_map::TileMap,
+    world::WorldPosition,
+};
+
+/// Keeps the camera on the player once they moved or changed level.
+pub struct Framing {}
+
+impl<'a> System<'a> for Framing {
+    type SystemData = (
+        Write<'a, Camera>,
+        Read<'a, TileMap>,
+        ReadStorage<'a, PlayerControlled>,
+        ReadStorage<'a, WorldPosition>,
+    );
+
+    fn run(&mut self, (mut camera, tile_map, player_controlled, world_position): Self::SystemData) {
+        if let Some((_, player_position)) = (&player_controlled, &world_position).join().next() {
+            camera.follow(*player_position, tile_map.width(), tile_map.height());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::WorldExt;
+
+    use crate::game::{
+        camera::Camera, common::UIEvent, test_harness::TestWorld, world::WorldPosition,
+    };
+
+    #[test]
+    fn the_camera_follows_the_player() {
+        let mut test_world = TestWorld::new(200, 200);
+        test_world.player(150, 5);
+
+        test_world.send(vec![]);
+        assert_eq!(
+            test_world.simulation.world.read_resource::<Camera>().origin,
+            WorldPosition { x: 135, y: 0 }
+        );
+
+        test_world.press(UIEvent::Down);
+        let camera = *test_world.simulation.world.read_resource::<Camera>();
+        assert!(camera.contains(WorldPosition { x: 150, y: 6 }));
+        assert_eq!(
+            camera
+                .to_canvas(WorldPosition { x: 150, y: 6 })
+                .map(|p| p.x),
+            Some(750.0)
+        );
+    }
+}
@@ -0,0 +1,19 @@
+use specs::prelude::*;
+
+use crate::game::{spatial_index::SpatialIndex, world::WorldPosition};
+
+/// Catches the `SpatialIndex` up with the positions changed since the last dispatch, and with the
+/// entities of a newly generated level, before the systems only reading it run.
+pub struct Indexing {}
+
+impl<'a> System<'a> for Indexing {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, WorldPosition>,
+        WriteExpect<'a, SpatialIndex>,
+    );
+
+    fn run(&mut self, (entities, world_position, mut spatial_index): Self::SystemData) {
+        spatial_index.maintain(&entities, &world_position);
+    }
+}
@@ -0,0 +1,266 @@
+use shrev::EventChannel;
+use specs::prelude::*;
+
+use crate::game::{
+    components::{
+        damageable::Damageable,
+        equippable::Equippable,
+        equipped::Equipped,
+        initiative::{Action, Initiative},
+        inventoried::{Inventoried, ItemCommand},
+        level::Level,
+        parent::Parent,
+        pickupable::Pickupable,
+        stackable::Stackable,
+        status_effects::StatusEffects,
+        usable::{ItemEffect, Usable},
+    },
+    events::GameEvent,
+    tile_map::TileMap,
+    world::WorldPosition,
+};
+
+/// Uses, drops and equips items for the inventoried entities that were told to.
+///
+/// Commands pointing past the end of the inventory, or at an item which can't do what was asked,
+/// are dropped without spending the entity's turn. That includes healing at full health.
+pub struct Inventory {}
+
+#[derive(SystemData)]
+pub struct InventoryData<'a> {
+    entities: Entities<'a>,
+    inventoried: WriteStorage<'a, Inventoried>,
+    initiative: WriteStorage<'a, Initiative>,
+    parent: WriteStorage<'a, Parent>,
+    world_position: WriteStorage<'a, WorldPosition>,
+    level: ReadStorage<'a, Level>,
+    pickupable: ReadStorage<'a, Pickupable>,
+    stackable: WriteStorage<'a, Stackable>,
+    usable: ReadStorage<'a, Usable>,
+    equippable: ReadStorage<'a, Equippable>,
+    equipped: WriteStorage<'a, Equipped>,
+    damageable: WriteStorage<'a, Damageable>,
+    status_effects: WriteStorage<'a, StatusEffects>,
+    tile_map: Write<'a, TileMap>,
+    events: Write<'a, EventChannel<GameEvent>>,
+}
+
+impl Inventory {
+    fn use_item(data: &mut InventoryData, owner: Entity, item: Entity) -> Option<Action> {
+        let effect = data.usable.get(item)?.effect;
+        match effect {
+            ItemEffect::RevealMap => data.tile_map.explore_all(),
+            ItemEffect::Heal(health) => {
+                // Not wasted on a user who has nothing to heal
+                let damageable = data
+                    .damageable
+                    .get_mut(owner)
+                    .filter(|damageable| damageable.health < damageable.max_health)?;
+                let amount = health.min(damageable.max_health - damageable.health);
+                damageable.health += amount;
+                data.events.single_write(GameEvent::Healed {
+                    entity: owner,
+                    amount,
+                });
+            }
+            ItemEffect::Afflict(effect) => {
+                if StatusEffects::afflict(&mut data.status_effects, owner, effect) {
+                    data.events.single_write(GameEvent::Afflicted {
+                        entity: owner,
+                        kind: effect.kind,
+                    });
+                }
+            }
+        }
+
+        // Stacks go down one at a time
+        match data.stackable.get_mut(item) {
+            Some(stackable) if stackable.count > 1 => stackable.count -= 1,
+            _ => data.entities.delete(item).unwrap(),
+        }
+        Some(Action::UseItem)
+    }
+
+    fn drop_item(data: &mut InventoryData, owner: Entity, item: Entity) -> Option<Action> {
+        let position = *data.world_position.get(owner)?;
+        let level = (&data.entities, &data.level)
+            .join()
+            .find(|(_, level)| level.active)
+            .map(|(entity, _)| entity)?;
+
+        data.equipped.remove(item);
+        data.parent.insert(item, Parent { entity: level }).unwrap();
+        data.world_position.insert(item, position).unwrap();
+        Some(Action::DropItem)
+    }
+
+    /// Equipping an item which is already equipped takes it off.
+    fn equip_item(data: &mut InventoryData, items: &[Entity], item: Entity) -> Option<Action> {
+        let slot = data.equippable.get(item)?.slot;
+        if data.equipped.remove(item).is_none() {
+            for other in items {
+                if data
+                    .equippable
+                    .get(*other)
+                    .is_some_and(|equippable| equippable.slot == slot)
+                {
+                    data.equipped.remove(*other);
+                }
+            }
+            data.equipped.insert(item, Equipped).unwrap();
+        }
+        Some(Action::EquipItem)
+    }
+}
+
+impl<'a> System<'a> for Inventory {
+    type SystemData = InventoryData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let commands: Vec<(Entity, ItemCommand)> = (&data.entities, &mut data.inventoried)
+            .join()
+            .filter_map(|(owner, inventoried)| Some((owner, inventoried.command.take()?)))
+            .collect();
+
+        for (owner, command) in commands {
+            let items = Inventoried::items(owner, &data.entities, &data.parent, &data.pickupable);
+            let (ItemCommand::Use(index) | ItemCommand::Drop(index) | ItemCommand::Equip(index)) =
+                command;
+            let Some(item) = items.get(index).copied() else {
+                continue;
+            };
+
+            let action = match command {
+                ItemCommand::Use(_) => Inventory::use_item(&mut data, owner, item),
+                ItemCommand::Drop(_) => Inventory::drop_item(&mut data, owner, item),
+                ItemCommand::Equip(_) => Inventory::equip_item(&mut data, &items, item),
+            };
+            if let (Some(action), Some(initiative)) = (action, data.initiative.get_mut(owner)) {
+                initiative.spend(action);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        common::UIEvent,
+        components::{damageable::Damageable, initiative::ACTION_THRESHOLD},
+        test_harness::TestWorld,
+    };
+
+    #[test]
+    fn using_an_item_uses_up_one_of_the_stack() {
+        let mut test_world = TestWorld::new(30, 30);
+        let player = test_world.player(1, 1);
+        let scrolls = test_world.scroll(2, 1);
+        test_world.scroll(3, 1);
+        test_world.press(UIEvent::Right);
+        test_world.press(UIEvent::Right);
+        assert_eq!(test_world.stack_count(scrolls), Some(2));
+        assert!(!test_world.explored(29, 29));
+
+        test_world.press(UIEvent::UseItem(0));
+
+        assert!(test_world.explored(29, 29));
+        assert_eq!(test_world.stack_count(scrolls), Some(1));
+        test_world.press(UIEvent::UseItem(0));
+        assert!(!test_world.is_alive(scrolls));
+        test_world.send(vec![]);
+        assert!(test_world.children(player).is_empty());
+    }
+
+    #[test]
+    fn potions_heal_up_to_full_health() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        test_world.set_defenses(
+            player,
+            Damageable {
+                health: 85,
+                ..Damageable::new(100)
+            },
+        );
+        let potions = test_world.potion(2, 1, 10);
+        test_world.potion(3, 1, 10);
+        test_world.press(UIEvent::Right);
+        test_world.press(UIEvent::Right);
+
+        test_world.press(UIEvent::UseItem(0));
+        assert_eq!(test_world.health(player), Some(95));
+        test_world.send(vec![]);
+        test_world.press(UIEvent::UseItem(0));
+        assert_eq!(test_world.health(player), Some(100));
+        assert!(!test_world.is_alive(potions));
+        assert!(test_world.messages().ends_with(&[
+            "You recover 10 health.".to_owned(),
+            "You recover 5 health.".to_owned()
+        ]));
+    }
+
+    #[test]
+    fn potions_are_kept_at_full_health() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        let potion = test_world.potion(2, 1, 10);
+        test_world.press(UIEvent::Right);
+
+        test_world.press(UIEvent::UseItem(0));
+
+        assert_eq!(test_world.stack_count(potion), Some(1));
+        assert!(test_world.energy(player) >= ACTION_THRESHOLD);
+    }
+
+    #[test]
+    fn dropped_items_stay_where_they_were_dropped() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        let gold = test_world.gold(2, 1);
+        test_world.press(UIEvent::Right);
+        assert_eq!(test_world.parent(gold), Some(player));
+
+        test_world.press(UIEvent::DropItem(0));
+        test_world.send(vec![]);
+
+        assert_eq!(test_world.parent(gold), Some(test_world.level()));
+        assert_eq!(test_world.entities_at(2, 1), vec![player, gold]);
+
+        // Stepping off and back onto the item picks it up again
+        test_world.press(UIEvent::Left);
+        test_world.press(UIEvent::Right);
+        assert_eq!(test_world.parent(gold), Some(player));
+    }
+
+    #[test]
+    fn only_one_weapon_is_equipped_at_once() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        let dagger = test_world.weapon(2, 1, 2);
+        let sword = test_world.weapon(3, 1, 4);
+        test_world.press(UIEvent::Right);
+        test_world.press(UIEvent::Right);
+
+        test_world.press(UIEvent::EquipItem(0));
+        assert!(test_world.is_equipped(dagger));
+        test_world.press(UIEvent::EquipItem(1));
+        assert!(!test_world.is_equipped(dagger));
+        assert!(test_world.is_equipped(sword));
+        test_world.press(UIEvent::EquipItem(1));
+        assert!(!test_world.is_equipped(sword));
+        assert_eq!(test_world.children(player).len(), 2);
+    }
+
+    #[test]
+    fn commands_for_missing_items_take_no_time() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        test_world.gold(2, 1);
+        test_world.press(UIEvent::Right);
+
+        test_world.press(UIEvent::UseItem(0));
+        test_world.press(UIEvent::EquipItem(3));
+
+        assert!(test_world.energy(player) >= ACTION_THRESHOLD);
+    }
+}
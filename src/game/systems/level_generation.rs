@@ -1,56 +1,44 @@
 use specs::prelude::*;
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use crate::game::{
-    common::Color,
-    components::{
-        ai_controlled::AIControlled,
-        armed::Armed,
-        climbable::Climbable,
-        collidable::Collidable,
-        damageable::Damageable,
-        describable::Describable,
-        factioned::{Faction, Factioned},
-        inventoried::Inventoried,
-        level::Level,
-        movable::{Direction, Movable},
-        opaque::Opaque,
-        parent::Parent,
-        pickupable::Pickupable,
-        player_controlled::PlayerControlled,
-        rendered::{Render, ZLayer},
-        sighted::Sighted,
-    },
+    algorithms::generation::{vaults::stamp_vaults, GeneratorKind},
+    components::{level::Level, parent::Parent},
     hierarchy::Hierarchy,
-    random::{random_in_range, random_in_vec_and_remove},
-    world::{WorldParameters, WorldPosition, WorldPositionLookupTable},
+    random::{RandomGenerator, Randomness},
+    spawner::Spawner,
+    tile_map::{Tile, TileMap},
+    world::{WorldParameters, WorldPosition},
 };
 
 pub struct LevelGeneration {}
 
+impl LevelGeneration {
+    /// Removes and returns a random open position for which `allowed` holds, or any open
+    /// position if there is none, as vaults may have been stamped over a whole room.
+    fn take_position(
+        open: &mut Vec<WorldPosition>,
+        rng: &mut RandomGenerator,
+        allowed: impl Fn(&WorldPosition) -> bool,
+    ) -> Option<WorldPosition> {
+        let mut candidates: Vec<usize> = (0..open.len()).filter(|i| allowed(&open[*i])).collect();
+        if candidates.is_empty() {
+            candidates = (0..open.len()).collect();
+        }
+        let index = *rng.random_in_vec(&candidates)?;
+        Some(open.remove(index))
+    }
+}
+
 impl<'a> System<'a> for LevelGeneration {
     type SystemData = (
         Entities<'a>,
         Read<'a, WorldParameters>,
-        Write<'a, WorldPositionLookupTable>,
+        Write<'a, Randomness>,
         WriteStorage<'a, Level>,
-        WriteStorage<'a, WorldPosition>,
-        WriteStorage<'a, Render>,
-        WriteStorage<'a, PlayerControlled>,
-        WriteStorage<'a, Movable>,
-        WriteStorage<'a, Collidable>,
-        WriteStorage<'a, Pickupable>,
-        WriteStorage<'a, Inventoried>,
-        WriteStorage<'a, Factioned>,
-        WriteStorage<'a, AIControlled>,
-        WriteStorage<'a, Damageable>,
-        WriteStorage<'a, Armed>,
-        WriteStorage<'a, Sighted>,
-        WriteStorage<'a, Opaque>,
-        WriteStorage<'a, Describable>,
-        WriteStorage<'a, Climbable>,
-        WriteStorage<'a, Parent>,
+        Spawner<'a>,
         ReadExpect<'a, Hierarchy<Parent>>,
+        Write<'a, TileMap>,
     );
 
     fn run(
@@ -58,265 +46,336 @@ impl<'a> System<'a> for LevelGeneration {
         (
             entities,
             world_parameters,
-            mut world_position_lookup_table,
+            mut randomness,
             mut level,
-            mut world_position,
-            mut render,
-            mut player_controlled,
-            mut movable,
-            mut collidable,
-            mut pickupable,
-            mut inventoried,
-            mut factioned,
-            mut ai_controlled,
-            mut damageable,
-            mut armed,
-            mut sighted,
-            mut opaque,
-            mut describable,
-            mut climbable,
-            mut parent,
+            mut spawner,
             hierarchy,
+            mut tile_map,
         ): Self::SystemData,
     ) {
         for (level_entity, level) in (&entities, &mut level).join() {
             if level.generated {
-                return;
+                continue;
             }
             level.generated = true;
+            level.ambient_light = Level::ambient_light_at(level.depth);
+            let rng = &mut randomness.level_generation;
 
-            let floor_render = Render {
-                glyph: '.'.into(),
-                foreground_color: Color::mildew(),
-                background_color: Color::brown().into(),
-                z_layer: ZLayer::Ground,
-            };
+            let generator = level
+                .generator
+                .unwrap_or_else(|| *rng.random_in_vec(&GeneratorKind::ALL).unwrap());
+            level.generator = Some(generator);
+            let mut layout = generator.generator().generate(
+                world_parameters.width,
+                world_parameters.height,
+                rng,
+            );
+            stamp_vaults(
+                &mut layout,
+                spawner.content.vaults.values(),
+                level.depth,
+                rng,
+            );
 
-            let stone_render = Render {
-                glyph: '#'.into(),
-                foreground_color: Color::brown(),
-                background_color: Color::black().into(),
-                z_layer: ZLayer::Saturating,
-            };
+            // Vault cells aren't floor in the layout, so nothing else gets placed in vaults.
+            // When there are rooms, the player starts in the first one next to the stairs up, the
+            // stairs down are in the last one and nothing else spawns in the player's room
+            let mut open = layout.floor_positions();
+            let start_room = layout.rooms.first().copied();
+            let end_room = layout.rooms.last().copied();
+            let has_rooms_apart = layout.rooms.len() > 1;
 
-            let stairs_render = Render {
-                glyph: '>'.into(),
-                foreground_color: Color::mildew(),
-                background_color: Color::brown().into(),
-                z_layer: ZLayer::Saturating,
-            };
-
-            let mut automata: Vec<WorldPosition> = vec![];
-            let mut carved = HashSet::<WorldPosition>::new();
-
-            for _ in 0..3 {
-                let x = random_in_range(0, world_parameters.width);
-                let y = random_in_range(0, world_parameters.height);
-                let position = WorldPosition { x, y };
-                automata.push(position);
-                carved.insert(position);
+            let mut stairs: HashMap<WorldPosition, &str> = HashMap::new();
+            if let Some(position) = LevelGeneration::take_position(&mut open, rng, |position| {
+                end_room.is_none_or(|room| room.contains(*position))
+            }) {
+                stairs.insert(position, "stairs");
             }
-
-            for _ in 0..1000 {
-                for pos in automata.iter_mut() {
-                    *pos = pos.moved(
-                        Direction::random(),
-                        world_parameters.width,
-                        world_parameters.height,
-                    );
-                    carved.insert(*pos);
-                }
+            let up_stairs = if level.depth > 1 {
+                LevelGeneration::take_position(&mut open, rng, |position| {
+                    start_room.is_none_or(|room| room.contains(*position))
+                })
+            } else {
+                None
+            };
+            if let Some(position) = up_stairs {
+                stairs.insert(position, "up_stairs");
             }
 
-            let stairs_positions: HashSet<WorldPosition> =
-                carved.iter().take(1).map(|p| *p).collect();
-
+            // Only the stairs and what vaults hold are entities, the rest is terrain
+            let mut tiles = TileMap::new(
+                world_parameters.width,
+                world_parameters.height,
+                Tile::StoneWall,
+            );
             for x in 0..world_parameters.width {
                 for y in 0..world_parameters.height {
                     let position = WorldPosition { x, y };
-                    if stairs_positions.contains(&position) {
-                        let entity = entities
-                            .build_entity()
-                            .with(Parent { entity: level_entity }, &mut parent)
-                            .with(position.clone(), &mut world_position)
-                            .with(stairs_render.clone(), &mut render)
-                            .with(
-                                Describable {
-                                    description: "Stairs".to_owned(),
-                                },
-                                &mut describable,
-                            )
-                            .with(Climbable::default(), &mut climbable)
-                            .build();
-                        world_position_lookup_table.update(entity, position);
-                    } else if carved.contains(&position) {
-                        let entity = entities
-                            .build_entity()
-                            .with(Parent { entity: level_entity }, &mut parent)
-                            .with(WorldPosition { x, y }, &mut world_position)
-                            .with(
-                                Describable {
-                                    description: "Floor".to_owned(),
-                                },
-                                &mut describable,
-                            )
-                            .with(floor_render.clone(), &mut render)
-                            .build();
-                        world_position_lookup_table.update(entity, WorldPosition { x, y });
-                    } else {
-                        let entity = entities
-                            .build_entity()
-                            .with(Parent { entity: level_entity }, &mut parent)
-                            .with(WorldPosition { x, y }, &mut world_position)
-                            .with(
-                                Describable {
-                                    description: "Stone Wall".to_owned(),
-                                },
-                                &mut describable,
-                            )
-                            .with(stone_render.clone(), &mut render)
-                            .with(Collidable {}, &mut collidable)
-                            .with(Opaque::default(), &mut opaque)
-                            .build();
-                        world_position_lookup_table.update(entity, WorldPosition { x, y });
+                    if let Some(templates) = layout.vault_cells.get(&position) {
+                        for template in templates {
+                            match Tile::from_name(template) {
+                                Some(tile) => tiles.set(position, tile),
+                                None => {
+                                    spawner.spawn(template, position, level_entity.into());
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                    if layout.is_floor(position) {
+                        tiles.set(position, Tile::Floor);
+                    }
+                    if let Some(template) = stairs.get(&position) {
+                        spawner.spawn(template, position, level_entity.into());
                     }
                 }
             }
-
-            let mut all_carved: Vec<_> = carved.iter().collect();
+            *tile_map = tiles;
 
             let mut old_player: Option<Entity> = None;
-            for (entity, _) in (&entities, &player_controlled).join() {
+            for (entity, _) in (&entities, &spawner.player_controlled).join() {
                 old_player = entity.into();
             }
 
-            if let Some(&player_position) = random_in_vec_and_remove(&mut all_carved) {
-                if let Some(old_player) = old_player {
-                    world_position.insert(old_player, player_position).unwrap();
-                    world_position_lookup_table.update(old_player, player_position);
+            // Players coming down arrive next to the stairs up rather than on them, so that they
+            // don't go straight back up
+            let player_position =
+                LevelGeneration::take_position(&mut open, rng, |position| match up_stairs {
+                    Some(up_stairs) => {
+                        position.x.abs_diff(up_stairs.x) + position.y.abs_diff(up_stairs.y) == 1
+                    }
+                    None => start_room.is_none_or(|room| room.contains(*position)),
+                });
+            if let Some(player_position) = player_position {
+                match old_player {
+                    Some(old_player) => spawner.place(old_player, player_position),
+                    None => {
+                        spawner.spawn("player", player_position, None);
+                    }
                 }
-                else {
-                    let character_render = Render {
-                        glyph: '@'.into(),
-                        foreground_color: Color::black(),
-                        background_color: None,
-                        z_layer: ZLayer::Creature,
-                    };
+            }
 
-                    let entity = entities
-                        .build_entity()
-                        .with(player_position.clone(), &mut world_position)
-                        .with(character_render.clone(), &mut render)
-                        .with(
-                            Describable {
-                                description: "Player".to_owned(),
-                            },
-                            &mut describable,
-                        )
-                        .with(PlayerControlled::default(), &mut player_controlled)
-                        .with(Movable::default(), &mut movable)
-                        .with(Inventoried::default(), &mut inventoried)
-                        .with(
-                            Factioned {
-                                faction: Faction::Player,
-                            },
-                            &mut factioned,
-                        )
-                        .with(Collidable {}, &mut collidable)
-                        .with(
-                            Damageable {
-                                health: 100,
-                                max_health: 100,
-                            },
-                            &mut damageable,
-                        )
-                        .with(
-                            Armed {
-                                damage: 5,
-                                targetting: None,
-                            },
-                            &mut armed,
-                        )
-                        .with(Sighted::default(), &mut sighted)
-                        .build();
-                    world_position_lookup_table.update(entity, player_position);
+            let away_from_player = |position: &WorldPosition| {
+                !has_rooms_apart || start_room.is_some_and(|room| !room.contains(*position))
+            };
+            let depth = level.depth;
+            let spawns = spawner.content.spawns.clone();
+            for table in [&spawns.items, &spawns.monsters] {
+                for _ in 0..table.count_at(depth) {
+                    let Some(template) = table.roll(depth, rng) else {
+                        continue;
+                    };
+                    if let Some(position) =
+                        LevelGeneration::take_position(&mut open, rng, away_from_player)
+                    {
+                        spawner.spawn(template, position, level_entity.into());
+                    }
                 }
             }
+        }
+    }
+}
 
-            let item_render = Render {
-                glyph: '$'.into(),
-                foreground_color: Color::yellow(),
-                background_color: None,
-                z_layer: ZLayer::Item,
-            };
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
 
-            for _ in 0..10 {
-                if let Some(&item_position) = random_in_vec_and_remove(&mut all_carved) {
-                    let entity = entities
-                        .build_entity()
-                        .with(Parent { entity: level_entity }, &mut parent)
-                        .with(item_position.clone(), &mut world_position)
-                        .with(item_render.clone(), &mut render)
-                        .with(
-                            Describable {
-                                description: "Gold".to_owned(),
-                            },
-                            &mut describable,
-                        )
-                        .with(Pickupable::default(), &mut pickupable)
-                        .build();
-                    world_position_lookup_table.update(entity, item_position);
-                }
+    use crate::game::{
+        algorithms::{
+            generation::GeneratorKind,
+            pathfinding::{a_star, Obstacles},
+        },
+        camera::Camera,
+        components::{
+            climbable::Climbable, describable::Describable, level::Level,
+            player_controlled::PlayerControlled, rendered::Render,
+        },
+        content::Content,
+        simulation::Simulation,
+        spatial_index::SpatialData,
+        tile_map::TileMap,
+        world::{WorldParameters, WorldPosition},
+    };
+
+    fn generate(seed: u64) -> Vec<(WorldPosition, Option<char>)> {
+        let mut simulation = Simulation::new(
+            seed,
+            WorldParameters {
+                width: 30,
+                height: 30,
+            },
+        );
+        simulation.tick(vec![]);
+
+        let world_position = simulation.world.read_storage::<WorldPosition>();
+        let render = simulation.world.read_storage::<Render>();
+        let tile_map = simulation.world.read_resource::<TileMap>();
+        let mut layout: Vec<_> = (&world_position, &render)
+            .join()
+            .map(|(position, render)| (*position, render.glyph))
+            .chain(
+                tile_map
+                    .positions()
+                    .map(|position| (position, tile_map.get(position).unwrap().render().glyph)),
+            )
+            .collect();
+        layout.sort();
+        layout
+    }
+
+    #[test]
+    fn same_seed_generates_the_same_level() {
+        assert_eq!(generate(12345), generate(12345));
+        assert_ne!(generate(12345), generate(54321));
+    }
+
+    #[test]
+    fn generated_level_has_a_player_and_stairs() {
+        let mut simulation = Simulation::new(
+            1,
+            WorldParameters {
+                width: 30,
+                height: 30,
+            },
+        );
+        // Vaults come with entities of their own
+        simulation.world.write_resource::<Content>().vaults.clear();
+        simulation.tick(vec![]);
+
+        let world = &simulation.world;
+        assert!((&world.read_storage::<Level>()).join().all(|l| l.generated));
+        assert_eq!(world.read_storage::<PlayerControlled>().join().count(), 1);
+        assert_eq!(world.read_storage::<Climbable>().join().count(), 1);
+        assert_eq!(
+            world.read_storage::<WorldPosition>().join().count(),
+            // The stairs, the player and what was spawned on the floor, terrain isn't made of
+            // entities
+            1 + 1 + 10 + 5
+        );
+    }
+
+    #[test]
+    fn levels_can_be_larger_than_the_screen() {
+        let mut simulation = Simulation::new(
+            3,
+            WorldParameters {
+                width: 200,
+                height: 200,
+            },
+        );
+        simulation.tick(vec![]);
+
+        let world = &simulation.world;
+        let tile_map = world.read_resource::<TileMap>();
+        assert_eq!((tile_map.width(), tile_map.height()), (200, 200));
+        let player_position = (
+            &world.read_storage::<PlayerControlled>(),
+            &world.read_storage::<WorldPosition>(),
+        )
+            .join()
+            .map(|(_, position)| *position)
+            .next()
+            .unwrap();
+        assert!(tile_map.is_walkable(player_position));
+        assert!(world.read_resource::<Camera>().contains(player_position));
+    }
+
+    #[test]
+    fn deeper_levels_have_stairs_up_next_to_the_player() {
+        let mut simulation = Simulation::new(
+            7,
+            WorldParameters {
+                width: 30,
+                height: 20,
+            },
+        );
+        for level in (&mut simulation.world.write_storage::<Level>()).join() {
+            level.depth = 2;
+        }
+        simulation.tick(vec![]);
+
+        let world = &simulation.world;
+        let world_position = world.read_storage::<WorldPosition>();
+        let climbable = world.read_storage::<Climbable>();
+        let up_stairs = (&world_position, &climbable)
+            .join()
+            .filter(|(_, climbable)| **climbable == Climbable::Up)
+            .map(|(position, _)| *position)
+            .collect::<Vec<_>>();
+        assert_eq!(up_stairs.len(), 1);
+        assert_eq!(climbable.join().count(), 2);
+
+        let (player, _) = (&world_position, &world.read_storage::<PlayerControlled>())
+            .join()
+            .next()
+            .unwrap();
+        assert_eq!(
+            player.x.abs_diff(up_stairs[0].x) + player.y.abs_diff(up_stairs[0].y),
+            1
+        );
+    }
+
+    #[test]
+    fn players_can_reach_the_stairs() {
+        for (seed, generator) in (0..9).zip(GeneratorKind::ALL.iter().cycle()) {
+            let mut simulation = Simulation::new(
+                seed,
+                WorldParameters {
+                    width: 30,
+                    height: 20,
+                },
+            );
+            for level in (&mut simulation.world.write_storage::<Level>()).join() {
+                level.generator = Some(*generator);
             }
+            simulation.tick(vec![]);
 
-            let monster_render = Render {
-                glyph: 'm'.into(),
-                foreground_color: Color::deep_red(),
-                background_color: None,
-                z_layer: ZLayer::Creature,
+            let world = &simulation.world;
+            let world_position = world.read_storage::<WorldPosition>();
+            let position_of = |marker: &dyn Fn(Entity) -> bool| {
+                (&world.entities(), &world_position)
+                    .join()
+                    .find(|(entity, _)| marker(*entity))
+                    .map(|(_, position)| *position)
+                    .unwrap()
             };
+            let player =
+                position_of(&|entity| world.read_storage::<PlayerControlled>().contains(entity));
+            let stairs = position_of(&|entity| world.read_storage::<Climbable>().contains(entity));
+            drop(world_position);
 
-            for _ in 0..5 {
-                if let Some(&monster_position) = random_in_vec_and_remove(&mut all_carved) {
-                    let entity = entities
-                        .build_entity()
-                        .with(Parent { entity: level_entity }, &mut parent)
-                        .with(monster_position.clone(), &mut world_position)
-                        .with(monster_render.clone(), &mut render)
-                        .with(
-                            Describable {
-                                description: "Monster".to_owned(),
-                            },
-                            &mut describable,
-                        )
-                        .with(AIControlled::default(), &mut ai_controlled)
-                        .with(Movable::default(), &mut movable)
-                        .with(Inventoried::default(), &mut inventoried)
-                        .with(
-                            Factioned {
-                                faction: Faction::Enemy,
-                            },
-                            &mut factioned,
-                        )
-                        .with(Collidable {}, &mut collidable)
-                        .with(
-                            Damageable {
-                                health: 10,
-                                max_health: 10,
-                            },
-                            &mut damageable,
-                        )
-                        .with(
-                            Armed {
-                                damage: 1,
-                                targetting: None,
-                            },
-                            &mut armed,
-                        )
-                        .build();
-                    world_position_lookup_table.update(entity, monster_position);
-                }
-            }
+            // Creatures move around, so only walls are in the way
+            let obstacles = Obstacles::from_terrain(
+                &world.read_resource::<TileMap>(),
+                world.system_data::<SpatialData>().index(),
+                |_| false,
+            );
+            assert!(a_star(&obstacles, player, stairs).is_some());
+        }
+    }
+
+    #[test]
+    fn vaults_are_stamped_into_levels() {
+        let mut simulation = Simulation::new(
+            3,
+            WorldParameters {
+                width: 30,
+                height: 30,
+            },
+        );
+        {
+            let mut content = simulation.world.write_resource::<Content>();
+            content.vaults.retain(|name, _| name == "shrine");
+            content.vaults.get_mut("shrine").unwrap().chance = 1.0;
         }
+        simulation.tick(vec![]);
+
+        // Gems only spawn on their own deeper down
+        let describable = simulation.world.read_storage::<Describable>();
+        let gems = describable
+            .join()
+            .filter(|describable| describable.description == "Gem")
+            .count();
+        assert_eq!(gems, 1);
     }
 }
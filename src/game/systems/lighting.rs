@@ -0,0 +1,134 @@
+use std::collections::HashSet;
+
+use specs::prelude::*;
+
+use crate::game::{
+    components::{level::Level, light_source::LightSource, opaque::Opaque},
+    light_map::LightMap,
+    tile_map::TileMap,
+    world::WorldPosition,
+};
+
+/// Works out the light map of the level the player is on, from the level's ambient light and the
+/// light sources on it.
+///
+/// The light map is only worked out again once the level, its terrain, an opaque entity or a light
+/// source changed.
+#[derive(Default)]
+pub struct Lighting {
+    level: Option<(Entity, f64)>,
+    terrain_revision: u64,
+    opaque_positions: HashSet<WorldPosition>,
+    light_sources: Vec<(WorldPosition, LightSource)>,
+}
+
+impl<'a> System<'a> for Lighting {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Level>,
+        Read<'a, TileMap>,
+        Write<'a, LightMap>,
+        ReadStorage<'a, WorldPosition>,
+        ReadStorage<'a, LightSource>,
+        ReadStorage<'a, Opaque>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            level,
+            tile_map,
+            mut light_map,
+            world_position,
+            light_source,
+            opaque,
+        ): Self::SystemData,
+    ) {
+        let active_level = (&entities, &level)
+            .join()
+            .find(|(_, level)| level.active)
+            .map(|(entity, level)| (entity, level.ambient_light));
+        let opaque_positions: HashSet<WorldPosition> = (&opaque, &world_position)
+            .join()
+            .map(|(_, opaque_world_position)| *opaque_world_position)
+            .collect();
+        let light_sources: Vec<(WorldPosition, LightSource)> = (&world_position, &light_source)
+            .join()
+            .map(|(light_world_position, light_source)| (*light_world_position, *light_source))
+            .collect();
+        if active_level == self.level
+            && tile_map.revision() == self.terrain_revision
+            && opaque_positions == self.opaque_positions
+            && light_sources == self.light_sources
+        {
+            return;
+        }
+
+        let ambient_light = active_level
+            .map(|(_, ambient_light)| ambient_light)
+            .unwrap_or(1.0);
+        *light_map = LightMap::new(tile_map.width(), tile_map.height(), ambient_light);
+        for (light_world_position, light_source) in &light_sources {
+            light_map.add_source(*light_world_position, light_source, |position| {
+                !tile_map.is_transparent(position) || opaque_positions.contains(&position)
+            });
+        }
+
+        self.level = active_level;
+        self.terrain_revision = tile_map.revision();
+        self.opaque_positions = opaque_positions;
+        self.light_sources = light_sources;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::WorldExt;
+
+    use crate::game::{light_map::LightMap, test_harness::TestWorld, world::WorldPosition};
+
+    #[test]
+    fn light_fades_and_stops_at_walls() {
+        let mut test_world = TestWorld::new(10, 10);
+        test_world.darken();
+        test_world.light(2, 2, 4);
+        test_world.wall(4, 2);
+
+        test_world.send(vec![]);
+
+        let light_map = test_world.simulation.world.read_resource::<LightMap>();
+        let brightness = |x, y| light_map.light_at(WorldPosition { x, y }).brightness();
+        assert!(brightness(2, 2) > brightness(2, 4));
+        assert!(light_map.is_lit(WorldPosition { x: 2, y: 4 }));
+        assert!(light_map.is_lit(WorldPosition { x: 4, y: 2 }));
+        assert!(!light_map.is_lit(WorldPosition { x: 5, y: 2 }));
+        assert!(!light_map.is_lit(WorldPosition { x: 2, y: 7 }));
+    }
+
+    #[test]
+    fn light_follows_its_source() {
+        let mut test_world = TestWorld::new(10, 10);
+        test_world.darken();
+        let light = test_world.light(2, 2, 2);
+        test_world.send(vec![]);
+        test_world.send(vec![]);
+        assert!(test_world
+            .simulation
+            .world
+            .read_resource::<LightMap>()
+            .is_lit(WorldPosition { x: 2, y: 2 }));
+
+        test_world
+            .simulation
+            .world
+            .write_storage::<WorldPosition>()
+            .insert(light, WorldPosition { x: 7, y: 7 })
+            .unwrap();
+        test_world.send(vec![]);
+
+        let light_map = test_world.simulation.world.read_resource::<LightMap>();
+        assert!(!light_map.is_lit(WorldPosition { x: 2, y: 2 }));
+        assert!(light_map.is_lit(WorldPosition { x: 7, y: 7 }));
+    }
+}
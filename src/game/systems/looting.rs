@@ -1,55 +1,262 @@
 use std::collections::HashMap;
 
+use shrev::EventChannel;
 use specs::prelude::*;
 
 use crate::game::{
-    components::{inventoried::Inventoried, pickupable::Pickupable, parent::Parent},
-    world::{WorldPosition, WorldPositionLookupTable},
+    components::{
+        initiative::{Action, Initiative},
+        inventoried::Inventoried,
+        movable::Movable,
+        parent::Parent,
+        pickupable::Pickupable,
+        stackable::Stackable,
+    },
+    events::GameEvent,
+    spatial_index::SpatialData,
 };
 
+/// Inventoried entities pick up the items they step on.
+///
+/// Only entities which `Movement` moved since the last run pick anything up, so that dropped items
+/// stay on the floor under whoever dropped them, and arriving on a level or loading a game picks
+/// nothing up. Items join a stack of their kind when there is one,
+/// otherwise they need a free place in the inventory.
 pub struct Looting {}
 
 impl<'a> System<'a> for Looting {
     type SystemData = (
-        Entities<'a>,
-        Write<'a, WorldPositionLookupTable>,
+        SpatialData<'a>,
+        WriteStorage<'a, Movable>,
         ReadStorage<'a, Pickupable>,
-        WriteStorage<'a, WorldPosition>,
         ReadStorage<'a, Inventoried>,
-        WriteStorage<'a, Parent>
+        WriteStorage<'a, Parent>,
+        WriteStorage<'a, Stackable>,
+        WriteStorage<'a, Initiative>,
+        Write<'a, EventChannel<GameEvent>>,
     );
 
     fn run(
         &mut self,
         (
-            entities,
-            mut world_position_lookup_table,
+            mut spatial_data,
+            mut movable,
             pickupable,
-            mut world_position,
             inventoried,
-            mut parent
+            mut parent,
+            mut stackable,
+            mut initiative,
+            mut events,
         ): Self::SystemData,
     ) {
+        let mut moved = BitSet::new();
+        for (entity, movable) in (&spatial_data.entities, &mut movable).join() {
+            if std::mem::take(&mut movable.moved) {
+                moved.add(entity.id());
+            }
+        }
+
+        let (entities, world_position, spatial_index) = spatial_data.split();
         let mut items_to_process: Vec<Entity> = vec![];
 
-        for (inventoried_entity, _, inventoried_world_position) in (&entities, &inventoried, &world_position).join()
+        for (inventoried_entity, inventoried, inventoried_world_position, _) in
+            (entities, &inventoried, &*world_position, &moved).join()
         {
-            if let Some(entities) = world_position_lookup_table.world_position_entities.get(&inventoried_world_position) {
-                for entity in entities {
-                    match (pickupable.get(*entity), parent.get_mut(*entity)) {
-                        (Some(_), Some(parent)) => {
-                            items_to_process.push(*entity);
-                            parent.entity = inventoried_entity;
-                        },
-                        _ => {}
+            let carried = Inventoried::items(inventoried_entity, entities, &parent, &pickupable);
+            let mut stacks: HashMap<String, Entity> = carried
+                .iter()
+                .filter_map(|item| Some((stackable.get(*item)?.kind.clone(), *item)))
+                .collect();
+            let mut places_taken = carried.len() as u32;
+
+            for entity in spatial_index.entities_at(*inventoried_world_position) {
+                if !pickupable.contains(entity) || !parent.contains(entity) {
+                    continue;
+                }
+
+                let kind = stackable
+                    .get(entity)
+                    .map(|stackable| stackable.kind.clone());
+                let stack = kind.as_ref().and_then(|kind| stacks.get(kind)).copied();
+                if let Some(stack) = stack {
+                    let count = stackable.get(entity).unwrap().count;
+                    stackable.get_mut(stack).unwrap().count += count;
+                    entities.delete(entity).unwrap();
+                } else if places_taken < inventoried.capacity {
+                    parent.get_mut(entity).unwrap().entity = inventoried_entity;
+                    places_taken += 1;
+                    if let Some(kind) = kind {
+                        stacks.insert(kind, entity);
                     }
+                } else {
+                    events.single_write(GameEvent::InventoryFull {
+                        entity: inventoried_entity,
+                    });
+                    continue;
+                }
+
+                events.single_write(GameEvent::PickedUp {
+                    entity: inventoried_entity,
+                    item: entity,
+                });
+                items_to_process.push(entity);
+                if let Some(initiative) = initiative.get_mut(inventoried_entity) {
+                    initiative.spend(Action::PickUp);
                 }
             }
         }
 
         for item in items_to_process {
             world_position.remove(item);
-            world_position_lookup_table.remove(item);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::game::{
+        common::UIEvent,
+        components::{
+            inventoried::Inventoried, level::Level, pickupable::Pickupable,
+            player_controlled::PlayerControlled,
+        },
+        test_harness::TestWorld,
+        tile_map::{Tile, TileMap},
+        world::WorldPosition,
+    };
+
+    fn find<C: Component>(test_world: &TestWorld) -> Entity {
+        let world = &test_world.simulation.world;
+        (&world.entities(), &world.read_storage::<C>())
+            .join()
+            .map(|(entity, _)| entity)
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn walking_over_items_picks_them_up() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        let gold = test_world.gold(6, 5);
+        let level = test_world.level();
+        assert_eq!(test_world.parent(gold), Some(level));
+
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(test_world.parent(gold), Some(player));
+        assert_eq!(test_world.position(gold), None);
+        assert_eq!(test_world.entities_at(6, 5), vec![player]);
+
+        // The hierarchy picks up the new parent on the next dispatch
+        test_world.send(vec![]);
+        assert_eq!(test_world.children(player), vec![gold]);
+        assert!(!test_world.children(level).contains(&gold));
+    }
+
+    #[test]
+    fn players_do_not_pick_up_walls() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.wall(6, 5);
+
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(test_world.tile(6, 5), Some(Tile::StoneWall));
+        assert!(test_world.children(player).is_empty());
+    }
+
+    #[test]
+    fn items_of_a_kind_stack() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        let gold = test_world.gold(2, 1);
+        let more_gold = test_world.gold(3, 1);
+
+        test_world.press(UIEvent::Right);
+        test_world.press(UIEvent::Right);
+        test_world.send(vec![]);
+
+        assert_eq!(test_world.children(player), vec![gold]);
+        assert_eq!(test_world.stack_count(gold), Some(2));
+        assert!(!test_world.is_alive(more_gold));
+    }
+
+    #[test]
+    fn full_inventories_leave_items_behind() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        test_world
+            .simulation
+            .world
+            .write_storage::<Inventoried>()
+            .insert(player, Inventoried::new(1))
+            .unwrap();
+        let gold = test_world.gold(2, 1);
+        let weapon = test_world.weapon(3, 1, 1);
+        let more_gold = test_world.gold(4, 1);
+
+        test_world.press(UIEvent::Right);
+        test_world.press(UIEvent::Right);
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(test_world.parent(weapon), Some(test_world.level()));
+        assert_eq!(
+            test_world.position(weapon),
+            Some(WorldPosition { x: 3, y: 1 })
+        );
+        assert_eq!(test_world.stack_count(gold), Some(2));
+        assert!(!test_world.is_alive(more_gold));
+    }
+
+    #[test]
+    fn loading_a_game_picks_nothing_up() {
+        let mut test_world = TestWorld::new(10, 10);
+        test_world.player(5, 5);
+        let gold = test_world.gold(5, 5);
+        test_world.send(vec![]);
+        assert_eq!(test_world.parent(gold), Some(test_world.level()));
+
+        let snapshot = test_world.simulation.save();
+        let mut loaded = TestWorld::new(3, 3);
+        loaded.simulation.load(&snapshot);
+        loaded.send(vec![]);
+
+        let gold = find::<Pickupable>(&loaded);
+        assert_eq!(loaded.parent(gold), Some(loaded.level()));
+        assert!(loaded
+            .children(find::<PlayerControlled>(&loaded))
+            .is_empty());
+    }
+
+    #[test]
+    fn arriving_on_a_level_picks_nothing_up() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.stairs(6, 5);
+        // Wherever next to the stairs the player comes back to
+        let golds = [(5, 5), (7, 5), (6, 4), (6, 6)].map(|(x, y)| test_world.gold(x, y));
+        let first_level = test_world.level();
+
+        test_world.press(UIEvent::Right);
+        for level in (&mut test_world.simulation.world.write_storage::<Level>()).join() {
+            level.generated = true;
+        }
+        test_world
+            .simulation
+            .world
+            .insert(TileMap::new(10, 10, Tile::Floor));
+        test_world.up_stairs(2, 2);
+        test_world.teleport(player, 2, 2);
+        test_world.send(vec![]);
+        test_world.send(vec![]);
+
+        assert_eq!(test_world.level(), first_level);
+        for gold in golds {
+            assert_eq!(test_world.parent(gold), Some(first_level));
+        }
+        assert!(test_world.children(player).is_empty());
+    }
+}
@@ -0,0 +1,312 @@
+use shrev::EventChannel;
+use specs::prelude::*;
+
+use crate::game::{
+    components::{
+        armed::DamageType, climbable::Climbable, describable::Describable,
+        player_controlled::PlayerControlled, sighted::Sighted,
+    },
+    events::GameEvent,
+    message_log::{MessageKind, MessageLog},
+    tile_map::TileMap,
+};
+
+/// Writes the game events the player would know about to the message log.
+///
+/// The player knows about what they do, what is done to them and what happens to the entities
+/// they see.
+pub struct Messages {
+    reader_id: ReaderId<GameEvent>,
+}
+
+impl Messages {
+    pub fn new(world: &mut World) -> Messages {
+        Messages {
+            reader_id: world
+                .entry::<EventChannel<GameEvent>>()
+                .or_insert_with(EventChannel::default)
+                .register_reader(),
+        }
+    }
+}
+
+#[derive(SystemData)]
+pub struct MessagesData<'a> {
+    events: Read<'a, EventChannel<GameEvent>>,
+    describable: ReadStorage<'a, Describable>,
+    player_controlled: ReadStorage<'a, PlayerControlled>,
+    sighted: ReadStorage<'a, Sighted>,
+    tile_map: Read<'a, TileMap>,
+    message_log: Write<'a, MessageLog>,
+}
+
+impl<'a> MessagesData<'a> {
+    fn is_player(&self, entity: Entity) -> bool {
+        self.player_controlled.contains(entity)
+    }
+
+    fn is_known(&self, entity: Entity) -> bool {
+        self.is_player(entity)
+            || (&self.player_controlled, &self.sighted)
+                .join()
+                .any(|(_, sighted)| sighted.seen.contains(entity.id()))
+    }
+
+    /// How the entity is called in the middle of a sentence.
+    fn name(&self, entity: Entity) -> String {
+        if self.is_player(entity) {
+            return "you".to_owned();
+        }
+        match self.describable.get(entity) {
+            Some(describable) => format!("the {}", describable.description.to_lowercase()),
+            None => "something".to_owned(),
+        }
+    }
+
+    /// The verb as it goes with the entity, the player being spoken to.
+    fn verb<'v>(&self, entity: Entity, you: &'v str, other: &'v str) -> &'v str {
+        if self.is_player(entity) {
+            you
+        } else {
+            other
+        }
+    }
+
+    fn message(&self, event: &GameEvent) -> Option<(String, MessageKind)> {
+        match *event {
+            GameEvent::Hit {
+                attacker,
+                target,
+                damage,
+                damage_type,
+                critical,
+            } => {
+                if !self.is_known(attacker) && !self.is_known(target) {
+                    return None;
+                }
+                let damage = match damage_type {
+                    DamageType::Physical => damage.to_string(),
+                    DamageType::Fire => format!("{} fire damage", damage),
+                    DamageType::Cold => format!("{} cold damage", damage),
+                    DamageType::Poison => format!("{} poison damage", damage),
+                };
+                let mut text = format!(
+                    "{} {} {} for {}.",
+                    self.name(attacker),
+                    self.verb(attacker, "hit", "hits"),
+                    self.name(target),
+                    damage
+                );
+                if critical {
+                    text += " A critical hit!";
+                }
+                let kind = if self.is_player(target) {
+                    MessageKind::Danger
+                } else {
+                    MessageKind::Combat
+                };
+                Some((text, kind))
+            }
+            GameEvent::Missed { attacker, target } => {
+                if !self.is_known(attacker) && !self.is_known(target) {
+                    return None;
+                }
+                let text = format!(
+                    "{} {} {}.",
+                    self.name(attacker),
+                    self.verb(attacker, "miss", "misses"),
+                    self.name(target)
+                );
+                Some((text, MessageKind::Combat))
+            }
+            GameEvent::Died { entity, .. } => {
+                if !self.is_known(entity) {
+                    return None;
+                }
+                let text = format!(
+                    "{} {}.",
+                    self.name(entity),
+                    self.verb(entity, "die", "dies")
+                );
+                let kind = if self.is_player(entity) {
+                    MessageKind::Danger
+                } else {
+                    MessageKind::Combat
+                };
+                Some((text, kind))
+            }
+            GameEvent::PickedUp { entity, item } => {
+                if !self.is_known(entity) {
+                    return None;
+                }
+                let item = self
+                    .describable
+                    .get(item)
+                    .map(|describable| describable.description.clone())
+                    .unwrap_or_else(|| "something".to_owned());
+                let text = format!(
+                    "{} {} {}.",
+                    self.name(entity),
+                    self.verb(entity, "pick up", "picks up"),
+                    item
+                );
+                Some((text, MessageKind::Loot))
+            }
+            GameEvent::InventoryFull { entity } if self.is_player(entity) => Some((
+                "There is no room left in your inventory.".to_owned(),
+                MessageKind::Info,
+            )),
+            GameEvent::Climbed {
+                entity,
+                direction,
+                depth,
+            } if self.is_player(entity) => {
+                let text = match direction {
+                    Climbable::Down => format!("You go down to depth {}.", depth),
+                    Climbable::Up => format!("You go back up to depth {}.", depth),
+                };
+                Some((text, MessageKind::Travel))
+            }
+            GameEvent::NoWayOut { entity } if self.is_player(entity) => Some((
+                "There is no way out of the dungeon.".to_owned(),
+                MessageKind::Info,
+            )),
+            GameEvent::Afflicted { entity, kind } => {
+                if !self.is_known(entity) {
+                    return None;
+                }
+                let text = format!(
+                    "{} {} {}.",
+                    self.name(entity),
+                    self.verb(entity, "are", "is"),
+                    kind.adjective()
+                );
+                let kind = if self.is_player(entity) && kind.is_harmful() {
+                    MessageKind::Danger
+                } else {
+                    MessageKind::Info
+                };
+                Some((text, kind))
+            }
+            GameEvent::Recovered { entity, kind } => {
+                if !self.is_known(entity) {
+                    return None;
+                }
+                let text = format!(
+                    "{} {} no longer {}.",
+                    self.name(entity),
+                    self.verb(entity, "are", "is"),
+                    kind.adjective()
+                );
+                Some((text, MessageKind::Info))
+            }
+            GameEvent::Healed { entity, amount } => {
+                if !self.is_known(entity) {
+                    return None;
+                }
+                let text = format!(
+                    "{} {} {} health.",
+                    self.name(entity),
+                    self.verb(entity, "recover", "recovers"),
+                    amount
+                );
+                Some((text, MessageKind::Info))
+            }
+            GameEvent::Poisoned { entity, damage } => {
+                if !self.is_known(entity) {
+                    return None;
+                }
+                let text = format!(
+                    "{} {} {} poison damage.",
+                    self.name(entity),
+                    self.verb(entity, "suffer", "suffers"),
+                    damage
+                );
+                let kind = if self.is_player(entity) {
+                    MessageKind::Danger
+                } else {
+                    MessageKind::Combat
+                };
+                Some((text, kind))
+            }
+            GameEvent::Bumped { entity, position } if self.is_player(entity) => {
+                let tile = self.tile_map.get(position)?;
+                let text = format!("You bump into the {}.", tile.description().to_lowercase());
+                Some((text, MessageKind::Info))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The sentence with its first letter in upper case.
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+impl<'a> System<'a> for Messages {
+    type SystemData = MessagesData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let messages: Vec<(String, MessageKind)> = data
+            .events
+            .read(&mut self.reader_id)
+            .filter_map(|event| data.message(event))
+            .collect();
+        for (text, kind) in messages {
+            data.message_log.push(capitalized(&text), kind);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{common::UIEvent, test_harness::TestWorld};
+
+    #[test]
+    fn fights_are_reported() {
+        let mut test_world = TestWorld::new(10, 10);
+        test_world.player(5, 5);
+        test_world.monster(6, 5, 10, 1);
+
+        test_world.press(UIEvent::Right);
+        test_world.send(vec![]);
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(
+            test_world.messages(),
+            vec![
+                "You hit the monster for 5.",
+                "The monster hits you for 1.",
+                "You hit the monster for 5.",
+                "The monster dies.",
+            ]
+        );
+    }
+
+    #[test]
+    fn pickups_and_stairs_are_reported() {
+        let mut test_world = TestWorld::new(10, 10);
+        test_world.player(5, 5);
+        test_world.gold(6, 5);
+        test_world.stairs(7, 5);
+        test_world.wall(5, 4);
+
+        test_world.press(UIEvent::Up);
+        test_world.press(UIEvent::Right);
+        test_world.press(UIEvent::Right);
+
+        assert_eq!(
+            test_world.messages(),
+            vec![
+                "You bump into the stone wall.",
+                "You pick up Gold.",
+                "You go down to depth 2.",
+            ]
+        );
+    }
+}
@@ -1,10 +1,21 @@
 pub mod ai;
 pub mod climbing;
 pub mod combat;
+pub mod death;
+pub mod effects;
+pub mod framing;
+pub mod indexing;
+pub mod inventory;
 pub mod level_generation;
+pub mod lighting;
 pub mod looting;
+pub mod messages;
 pub mod movement;
 pub mod perspective;
 pub mod player_command_handler;
+pub mod recovery;
+#[cfg(feature = "web")]
 pub mod rendering;
+pub mod scheduling;
+#[cfg(feature = "web")]
 pub mod ui;
@@ -1,95 +1,164 @@
+use shrev::EventChannel;
 use specs::prelude::*;
 
 use crate::game::{
-    components::{armed::Armed, collidable::Collidable, damageable::Damageable, movable::Movable},
-    world::{WorldParameters, WorldPosition, WorldPositionLookupTable},
+    components::{
+        armed::Armed,
+        collidable::Collidable,
+        damageable::Damageable,
+        initiative::{Action, Initiative},
+        movable::{Direction, Movable},
+        status_effects::{StatusEffectKind, StatusEffects, CONFUSED_STUMBLE_CHANCE},
+    },
+    events::GameEvent,
+    random::Randomness,
+    spatial_index::SpatialData,
+    tile_map::TileMap,
+    world::WorldParameters,
 };
 
 pub struct Movement {}
 
 impl<'a> System<'a> for Movement {
     type SystemData = (
-        Entities<'a>,
+        SpatialData<'a>,
         WriteStorage<'a, Movable>,
-        WriteStorage<'a, WorldPosition>,
         WriteStorage<'a, Armed>,
+        WriteStorage<'a, Initiative>,
         ReadStorage<'a, Collidable>,
         ReadStorage<'a, Damageable>,
+        ReadStorage<'a, StatusEffects>,
         Read<'a, WorldParameters>,
-        Write<'a, WorldPositionLookupTable>,
+        Read<'a, TileMap>,
+        Write<'a, EventChannel<GameEvent>>,
+        Write<'a, Randomness>,
     );
 
     fn run(
         &mut self,
         (
-            entities,
+            mut spatial_data,
             mut movable,
-            mut world_position,
             mut armed,
+            mut initiative,
             collidable,
             damageable,
+            status_effects,
             world_parameters,
-            mut world_position_lookup_table,
+            tile_map,
+            mut events,
+            mut randomness,
         ): Self::SystemData,
     ) {
-        for (entity, movable, world_position, armed) in (
-            &entities,
-            &mut movable,
-            &mut world_position,
-            (&mut armed).maybe(),
+        let moving: Vec<Entity> = (
+            &spatial_data.entities,
+            &movable,
+            &spatial_data.world_position,
         )
             .join()
-        {
-            self.apply_movement(
-                entity,
-                movable,
-                world_position,
-                &world_parameters,
-                &mut world_position_lookup_table,
-                &collidable,
-                &damageable,
-                armed,
-            );
-        }
-    }
-}
+            .filter(|(_, movable, _)| movable.unprocessed_move.is_some())
+            .map(|(entity, _, _)| entity)
+            .collect();
 
-impl Movement {
-    fn apply_movement(
-        &mut self,
-        entity: Entity,
-        movable: &mut Movable,
-        world_position: &mut WorldPosition,
-        world_parameters: &Read<WorldParameters>,
-        world_position_lookup_table: &mut WorldPositionLookupTable,
-        collidable: &ReadStorage<Collidable>,
-        damageable: &ReadStorage<Damageable>,
-        armed: Option<&mut Armed>,
-    ) {
-        if let Some(direction) = movable.unprocessed_move.take() {
-            let new_world_position =
-                world_position.moved(direction, world_parameters.width, world_parameters.height);
-            if let Some(entities) = world_position_lookup_table
-                .world_position_entities
-                .get(&new_world_position)
+        for entity in moving {
+            // Entities moved earlier in the loop are where they went
+            let (_, world_position, spatial_index) = spatial_data.split();
+
+            let mut direction = movable
+                .get_mut(entity)
+                .and_then(|movable| movable.unprocessed_move.take())
+                .unwrap();
+            if status_effects
+                .get(entity)
+                .is_some_and(|status_effects| status_effects.has(StatusEffectKind::Confusion))
+                && randomness.status_effects.next_f64() < CONFUSED_STUMBLE_CHANCE
             {
-                for entity in entities {
-                    match (collidable.get(*entity), damageable.get(*entity)) {
-                        (Some(_), Some(_)) => {
-                            if let Some(armed) = armed {
-                                armed.targetting = (*entity).into();
-                            }
-                            return;
-                        }
-                        (Some(_), None) => {
-                            return;
+                direction = Direction::random(&mut randomness.status_effects);
+            }
+            let new_world_position = world_position.get(entity).unwrap().moved(
+                direction,
+                world_parameters.width,
+                world_parameters.height,
+            );
+            let action = match spatial_index.find_at(new_world_position, &collidable) {
+                _ if !tile_map.is_walkable(new_world_position) => {
+                    events.single_write(GameEvent::Bumped {
+                        entity,
+                        position: new_world_position,
+                    });
+                    Some(Action::Move)
+                }
+                Some((other_entity, _)) if other_entity != entity => {
+                    match (damageable.get(other_entity), armed.get_mut(entity)) {
+                        // Attacking is taken care of by combat
+                        (Some(_), Some(armed)) => {
+                            armed.targetting = other_entity.into();
+                            None
                         }
-                        _ => {}
+                        _ => Some(Action::Move),
                     }
                 }
+                _ => {
+                    world_position.insert(entity, new_world_position).unwrap();
+                    movable.get_mut(entity).unwrap().moved = true;
+                    Some(Action::Move)
+                }
+            };
+            if let (Some(action), Some(initiative)) = (action, initiative.get_mut(entity)) {
+                initiative.spend(action);
             }
-            *world_position = new_world_position;
-            world_position_lookup_table.update(entity, new_world_position);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{common::UIEvent, test_harness::TestWorld, world::WorldPosition};
+
+    #[test]
+    fn player_moves_on_arrow_keys() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+
+        test_world.press(UIEvent::Right);
+        assert_eq!(
+            test_world.position(player),
+            Some(WorldPosition { x: 6, y: 5 })
+        );
+
+        test_world.press(UIEvent::Up);
+        test_world.press(UIEvent::Up);
+        assert_eq!(
+            test_world.position(player),
+            Some(WorldPosition { x: 6, y: 3 })
+        );
+        assert_eq!(test_world.entities_at(6, 3), vec![player]);
+        assert!(test_world.entities_at(5, 5).is_empty());
+    }
+
+    #[test]
+    fn walls_block_movement() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.wall(4, 5);
+
+        test_world.press(UIEvent::Left);
+        assert_eq!(
+            test_world.position(player),
+            Some(WorldPosition { x: 5, y: 5 })
+        );
+    }
+
+    #[test]
+    fn edges_of_the_world_block_movement() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(0, 9);
+
+        test_world.send(vec![UIEvent::Left]);
+        test_world.send(vec![UIEvent::Down]);
+        assert_eq!(
+            test_world.position(player),
+            Some(WorldPosition { x: 0, y: 9 })
+        );
+    }
+}
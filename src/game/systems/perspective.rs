@@ -1,75 +1,212 @@
-use std::{collections::HashSet, f64::consts::PI};
+use std::collections::HashSet;
 
 use specs::prelude::*;
 
 use crate::game::{
-    algorithms::raycasting::Raycast,
-    components::{movable::Movable, opaque::Opaque, sighted::Sighted},
-    world::{WorldParameters, WorldPosition},
+    algorithms::fov::field_of_view,
+    components::{
+        movable::Movable,
+        opaque::Opaque,
+        player_controlled::PlayerControlled,
+        sighted::Sighted,
+        status_effects::{StatusEffectKind, StatusEffects, BLIND_SIGHT_RADIUS},
+    },
+    light_map::LightMap,
+    tile_map::TileMap,
+    world::WorldPosition,
 };
 
-pub struct Perspective {}
-
-const MAX_CELL_DISTANCE: f64 = 20.0;
+/// Works out what every sighted entity sees.
+///
+/// Fields of view are only cast again for entities that moved, or for everyone once the terrain
+/// or an opaque entity has. Within them, only lit cells and the cells right next to the entity
+/// are made out. Blinded entities only see as far as `BLIND_SIGHT_RADIUS`.
+#[derive(Default)]
+pub struct Perspective {
+    terrain_revision: u64,
+    opaque_positions: HashSet<WorldPosition>,
+    blinded: BitSet,
+}
 
 impl<'a> System<'a> for Perspective {
     type SystemData = (
         Entities<'a>,
-        Read<'a, WorldParameters>,
+        Write<'a, TileMap>,
+        Read<'a, LightMap>,
         ReadStorage<'a, WorldPosition>,
         ReadStorage<'a, Opaque>,
         ReadStorage<'a, Movable>,
+        ReadStorage<'a, PlayerControlled>,
+        ReadStorage<'a, StatusEffects>,
         WriteStorage<'a, Sighted>,
     );
 
     fn run(
         &mut self,
-        (entities, world_parameters, world_position, opaque, movable, mut sighted): Self::SystemData,
+        (
+            entities,
+            mut tile_map,
+            light_map,
+            world_position,
+            opaque,
+            movable,
+            player_controlled,
+            status_effects,
+            mut sighted,
+        ): Self::SystemData,
     ) {
-        for (sighted_world_position, sighted) in (&world_position, &mut sighted).join() {
-            sighted.seen.clear();
+        // Terrain is looked up in the tile map, only the few opaque entities are gathered here
+        let opaque_positions: HashSet<WorldPosition> = (&opaque, &world_position)
+            .join()
+            .map(|(_, opaque_world_position)| *opaque_world_position)
+            .collect();
+        if tile_map.revision() != self.terrain_revision || opaque_positions != self.opaque_positions
+        {
+            for sighted in (&mut sighted).join() {
+                sighted.invalidate();
+            }
+            self.terrain_revision = tile_map.revision();
+            self.opaque_positions = opaque_positions;
+        }
 
-            let mut has_opaque = HashSet::<WorldPosition>::new();
-            for (_, opaque_world_position) in (&opaque, &world_position).join() {
-                has_opaque.insert(*opaque_world_position);
+        // Fields of view are cast again with the new radius when blindness comes or goes
+        let blinded: BitSet = (&entities, &status_effects)
+            .join()
+            .filter(|(_, status_effects)| status_effects.has(StatusEffectKind::Blindness))
+            .map(|(entity, _)| entity.id())
+            .collect();
+        for (_, sighted) in (&blinded ^ &self.blinded, &mut sighted).join() {
+            sighted.invalidate();
+        }
+        self.blinded = blinded;
+
+        for (sighted_entity, sighted_world_position, sighted) in
+            (&entities, &world_position, &mut sighted).join()
+        {
+            if sighted.viewpoint != Some(*sighted_world_position) {
+                let radius = if self.blinded.contains(sighted_entity.id()) {
+                    sighted.radius.min(BLIND_SIGHT_RADIUS)
+                } else {
+                    sighted.radius
+                };
+                sighted.visible = field_of_view(*sighted_world_position, radius, |position| {
+                    !tile_map.is_transparent(position) || self.opaque_positions.contains(&position)
+                });
+                sighted.viewpoint = Some(*sighted_world_position);
             }
 
-            let num_rays = (2.0 * PI * MAX_CELL_DISTANCE) as u32 * 2;
-            let mut radians = 0.0;
-            let radian_delta = (PI * 2.0) / num_rays as f64;
-
-            let mut seen_positions = HashSet::<WorldPosition>::new();
-            seen_positions.insert(*sighted_world_position);
-
-            for _ in 0..num_rays {
-                for ray_world_position in Raycast::new(
-                    *sighted_world_position,
-                    world_parameters.max_position(),
-                    radians - PI,
-                ) {
-                    seen_positions.insert(ray_world_position);
-                    if !has_opaque.contains(&ray_world_position)
-                        && sighted_world_position.distance_from(ray_world_position)
-                            < MAX_CELL_DISTANCE
-                    {
-                        seen_positions.insert(ray_world_position);
-                    } else {
-                        break;
-                    }
-                }
-                radians += radian_delta;
+            // Lights move around, so what is lit is checked every time
+            let made_out = |position: &WorldPosition| {
+                sighted.visible.contains(position)
+                    && (light_map.is_lit(*position)
+                        || sighted_world_position.distance_from(*position) < 1.5)
+            };
+            if player_controlled.contains(sighted_entity) {
+                tile_map.set_visible(sighted.visible.iter().copied().filter(made_out));
             }
 
+            let mut seen = BitSet::new();
+            let mut seen_recently = BitSet::new();
             for (entity, seen_world_position, movable) in
                 (&entities, &world_position, (&movable).maybe()).join()
             {
-                if seen_positions.contains(seen_world_position) {
-                    sighted.seen.add(entity.id());
+                if made_out(seen_world_position) {
+                    seen.add(entity.id());
                     if movable.is_none() {
-                        sighted.seen_recently.add(entity.id());
+                        seen_recently.add(entity.id());
                     }
                 }
             }
+            sighted.seen = seen;
+            sighted.seen_recently |= &seen_recently;
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use specs::WorldExt;
+
+    use crate::game::{components::sighted::Sighted, test_harness::TestWorld};
+
+    #[test]
+    fn walls_block_sight() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        test_world.wall(3, 1);
+        let hidden_gold = test_world.gold(5, 1);
+        let visible_gold = test_world.gold(1, 5);
+
+        test_world.send(vec![]);
+
+        assert!(test_world.sees(player, player));
+        assert!(test_world.sees_position(3, 1));
+        assert!(!test_world.sees_position(4, 1));
+        assert!(test_world.sees(player, visible_gold));
+        assert!(!test_world.sees(player, hidden_gold));
+    }
+
+    #[test]
+    fn sight_is_limited_by_distance() {
+        let mut test_world = TestWorld::new(30, 30);
+        let player = test_world.player(0, 0);
+        let near_gold = test_world.gold(10, 10);
+        let far_gold = test_world.gold(29, 29);
+
+        test_world.send(vec![]);
+
+        assert!(test_world.sees(player, near_gold));
+        assert!(!test_world.sees(player, far_gold));
+    }
+
+    #[test]
+    fn sight_follows_changes_to_the_terrain() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        let gold = test_world.gold(5, 1);
+
+        test_world.send(vec![]);
+        assert!(test_world.sees(player, gold));
+
+        test_world.wall(3, 1);
+        test_world.send(vec![]);
+        assert!(!test_world.sees(player, gold));
+    }
+
+    #[test]
+    fn every_entity_has_its_own_sight_radius() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(1, 1);
+        let monster = test_world.monster(7, 1, 10, 1);
+        test_world
+            .simulation
+            .world
+            .write_storage::<Sighted>()
+            .insert(monster, Sighted::new(3))
+            .unwrap();
+
+        test_world.send(vec![]);
+
+        assert!(test_world.sees(player, monster));
+        assert!(!test_world.sees(monster, player));
+    }
+
+    #[test]
+    fn only_lit_cells_are_seen_in_the_dark() {
+        let mut test_world = TestWorld::new(10, 10);
+        test_world.darken();
+        let player = test_world.player(1, 1);
+        let near_gold = test_world.gold(2, 2);
+        let far_gold = test_world.gold(5, 1);
+        let lit_gold = test_world.gold(8, 8);
+        test_world.light(8, 6, 3);
+
+        test_world.send(vec![]);
+
+        assert!(test_world.sees(player, near_gold));
+        assert!(!test_world.sees(player, far_gold));
+        assert!(!test_world.sees_position(5, 1));
+        assert!(test_world.sees(player, lit_gold));
+        assert!(test_world.sees_position(8, 6));
+    }
+}
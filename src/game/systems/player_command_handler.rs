@@ -3,45 +3,48 @@ use specs::prelude::*;
 use crate::game::{
     common::UIEvent,
     components::{
+        initiative::Initiative,
+        inventoried::{Inventoried, ItemCommand},
         movable::{Direction, Movable},
         player_controlled::PlayerControlled,
     },
-    world::{LastUserEvent, UIState, WorldTime},
+    world::{LastUserEvent, UIState},
 };
 
 pub struct PlayerCommandHandler {}
 
 impl<'a> System<'a> for PlayerCommandHandler {
     type SystemData = (
-        ReadStorage<'a, PlayerControlled>,
+        WriteStorage<'a, PlayerControlled>,
         WriteStorage<'a, Movable>,
+        WriteStorage<'a, Inventoried>,
+        ReadStorage<'a, Initiative>,
         Write<'a, LastUserEvent>,
-        Write<'a, WorldTime>,
         Write<'a, UIState>,
     );
 
     fn run(
         &mut self,
-        (player_controlled, mut movable, mut last_user_event, mut world_time, mut ui_state): Self::SystemData,
+        (
+            mut player_controlled,
+            mut movable,
+            mut inventoried,
+            initiative,
+            mut last_user_event,
+            mut ui_state,
+        ): Self::SystemData,
     ) {
         for user_event in last_user_event.events.iter() {
-            for (_, movable) in (&player_controlled, &mut movable).join() {
+            for player_controlled in (&mut player_controlled).join() {
                 match user_event {
-                    UIEvent::Down => {
-                        movable.unprocessed_move = Direction::Down.into();
-                        world_time.tick += 1;
-                    }
-                    UIEvent::Left => {
-                        movable.unprocessed_move = Direction::Left.into();
-                        world_time.tick += 1;
-                    }
-                    UIEvent::Right => {
-                        movable.unprocessed_move = Direction::Right.into();
-                        world_time.tick += 1;
-                    }
-                    UIEvent::Up => {
-                        movable.unprocessed_move = Direction::Up.into();
-                        world_time.tick += 1;
+                    UIEvent::Down
+                    | UIEvent::Left
+                    | UIEvent::Right
+                    | UIEvent::Up
+                    | UIEvent::UseItem(_)
+                    | UIEvent::DropItem(_)
+                    | UIEvent::EquipItem(_) => {
+                        player_controlled.unprocessed_action = (*user_event).into();
                     }
                     UIEvent::MouseOver(canvas_position) => {
                         ui_state.mouse_over = (*canvas_position).into();
@@ -52,5 +55,35 @@ impl<'a> System<'a> for PlayerCommandHandler {
             }
         }
         last_user_event.events.clear();
+
+        // Actions are kept until the player has enough energy to act on them
+        for (player_controlled, movable, mut inventoried, initiative) in (
+            &mut player_controlled,
+            &mut movable,
+            (&mut inventoried).maybe(),
+            (&initiative).maybe(),
+        )
+            .join()
+        {
+            if initiative.is_some_and(|initiative| !initiative.is_ready()) {
+                continue;
+            }
+            let action = player_controlled.unprocessed_action.take();
+            movable.unprocessed_move = match action {
+                Some(UIEvent::Down) => Direction::Down.into(),
+                Some(UIEvent::Left) => Direction::Left.into(),
+                Some(UIEvent::Right) => Direction::Right.into(),
+                Some(UIEvent::Up) => Direction::Up.into(),
+                _ => None,
+            };
+            if let Some(inventoried) = inventoried.as_mut() {
+                inventoried.command = match action {
+                    Some(UIEvent::UseItem(index)) => ItemCommand::Use(index).into(),
+                    Some(UIEvent::DropItem(index)) => ItemCommand::Drop(index).into(),
+                    Some(UIEvent::EquipItem(index)) => ItemCommand::Equip(index).into(),
+                    _ => None,
+                };
+            }
+        }
     }
 }
@@ -0,0 +1,123 @@
+use specs::prelude::*;
+
+use crate::game::{
+    components::{
+        damageable::Damageable, factioned::Factioned, regenerating::Regenerating, sighted::Sighted,
+    },
+    world::{WorldPosition, WorldTime},
+};
+
+/// Heals the regenerating entities over the turns the world advanced.
+///
+/// Resting only counts while no entity of another faction is in sight, and not at full health,
+/// so health can't be banked ahead of a fight. Stowed entities don't rest.
+pub struct Recovery {}
+
+#[derive(SystemData)]
+pub struct RecoveryData<'a> {
+    entities: Entities<'a>,
+    regenerating: WriteStorage<'a, Regenerating>,
+    damageable: WriteStorage<'a, Damageable>,
+    factioned: ReadStorage<'a, Factioned>,
+    sighted: ReadStorage<'a, Sighted>,
+    world_position: ReadStorage<'a, WorldPosition>,
+    world_time: Read<'a, WorldTime>,
+}
+
+impl<'a> RecoveryData<'a> {
+    fn sees_enemies(&self, entity: Entity) -> bool {
+        let (Some(sighted), Some(own)) = (self.sighted.get(entity), self.factioned.get(entity))
+        else {
+            return false;
+        };
+        (&self.entities, &self.factioned)
+            .join()
+            .any(|(other, factioned)| {
+                factioned.faction != own.faction && sighted.seen.contains(other.id())
+            })
+    }
+}
+
+impl<'a> System<'a> for Recovery {
+    type SystemData = RecoveryData<'a>;
+
+    fn run(&mut self, mut data: Self::SystemData) {
+        let tick = data.world_time.tick;
+        let resting: Vec<(Entity, u64)> =
+            (&data.entities, &mut data.regenerating, &data.world_position)
+                .join()
+                .map(|(entity, regenerating, _)| {
+                    let ticked_at = regenerating.ticked_at.replace(tick).unwrap_or(tick);
+                    (entity, tick - ticked_at)
+                })
+                .filter(|(_, turns)| *turns > 0)
+                .collect();
+
+        for (entity, turns) in resting {
+            if data.sees_enemies(entity) {
+                continue;
+            }
+            let (Some(regenerating), Some(damageable)) = (
+                data.regenerating.get_mut(entity),
+                data.damageable.get_mut(entity),
+            ) else {
+                continue;
+            };
+            // Already dying, or with nothing to heal
+            if damageable.health == 0 || damageable.health >= damageable.max_health {
+                regenerating.turns = 0;
+                continue;
+            }
+            regenerating.turns += turns as u32;
+            let points = regenerating.turns / regenerating.turns_per_point.max(1);
+            regenerating.turns %= regenerating.turns_per_point.max(1);
+            damageable.health = (damageable.health + points).min(damageable.max_health);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{components::damageable::Damageable, test_harness::TestWorld};
+
+    fn wounded(health: u32) -> Damageable {
+        Damageable {
+            health,
+            ..Damageable::new(100)
+        }
+    }
+
+    #[test]
+    fn health_comes_back_with_rest() {
+        let mut test_world = TestWorld::new(10, 10);
+        let player = test_world.player(5, 5);
+        test_world.set_defenses(player, wounded(50));
+        test_world.set_regeneration(player, 3);
+        test_world.send(vec![]);
+
+        test_world.pass_turns(2);
+        assert_eq!(test_world.health(player), Some(50));
+        test_world.pass_turns(7);
+        assert_eq!(test_world.health(player), Some(53));
+    }
+
+    #[test]
+    fn nobody_rests_with_enemies_in_sight() {
+        let mut test_world = TestWorld::new(20, 20);
+        let player = test_world.player(1, 5);
+        test_world.set_defenses(player, wounded(50));
+        test_world.set_regeneration(player, 1);
+        let monster = test_world.monster(18, 5, 10, 1);
+        test_world.set_speed(monster, 0);
+        test_world.send(vec![]);
+
+        test_world.pass_turns(4);
+        assert_eq!(test_world.health(player), Some(50));
+
+        test_world.wall(10, 4);
+        test_world.wall(10, 5);
+        test_world.wall(10, 6);
+        test_world.pass_turns(4);
+        assert!(test_world.health(player) > Some(50));
+    }
+}
@@ -4,11 +4,14 @@ use specs::prelude::*;
 use web_sys::CanvasRenderingContext2d;
 
 use crate::game::{
-    common::{CanvasSize, Color},
+    camera::Camera,
+    common::{CanvasPosition, CanvasSize, Color},
     components::{
         damageable::Damageable, player_controlled::PlayerControlled, rendered::Render,
         sighted::Sighted,
     },
+    light_map::LightMap,
+    tile_map::TileMap,
     world::CELL_SIZE,
     world::{UIState, WorldPosition},
 };
@@ -69,12 +72,31 @@ impl<'a> System<'a> for Rendering {
         ReadStorage<'a, PlayerControlled>,
         ReadStorage<'a, Sighted>,
         ReadStorage<'a, Damageable>,
+        Read<'a, TileMap>,
+        Read<'a, LightMap>,
+        Read<'a, Camera>,
         Read<'a, UIState>,
     );
 
+    /// The view is as big as the canvas, whatever the size of the levels.
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        world.insert(Camera::from_canvas_size(self.canvas_size));
+    }
+
     fn run(
         &mut self,
-        (pos, render, player_controlled, sighted, damageable, ui_state): Self::SystemData,
+        (
+            pos,
+            render,
+            player_controlled,
+            sighted,
+            damageable,
+            tile_map,
+            light_map,
+            camera,
+            ui_state,
+        ): Self::SystemData,
     ) {
         let x_text_offset = CELL_SIZE / 2.0;
         let y_text_offset = CELL_SIZE / 2.0;
@@ -101,6 +123,23 @@ impl<'a> System<'a> for Rendering {
 
             let mut hash_map: HashMap<WorldPosition, RenderTarget> = HashMap::new();
 
+            // Terrain goes first, below everything standing on it
+            for pos in camera.positions() {
+                if !tile_map.is_explored(pos) {
+                    continue;
+                }
+                if let Some(tile) = tile_map.get(pos) {
+                    let semi_renderable = !tile_map.is_visible(pos);
+                    Rendering::add_to_render_targets(
+                        &mut hash_map,
+                        &tile.render(),
+                        &pos,
+                        None,
+                        semi_renderable,
+                    );
+                }
+            }
+
             for (pos, render, health, _) in renderable {
                 Rendering::add_to_render_targets(&mut hash_map, render, pos, health, false);
             }
@@ -110,11 +149,13 @@ impl<'a> System<'a> for Rendering {
             }
 
             for (pos, render_target) in hash_map {
-                let x = CELL_SIZE * pos.x as f64;
-                let y = CELL_SIZE * pos.y as f64;
+                let Some(CanvasPosition { x, y }) = camera.to_canvas(pos) else {
+                    continue;
+                };
 
+                let light = light_map.light_at(pos);
                 let hovered = ui_state
-                    .mouse_over_position()
+                    .mouse_over_position(&camera)
                     .map(|p| p == pos)
                     .unwrap_or(false);
 
@@ -124,6 +165,8 @@ impl<'a> System<'a> for Rendering {
                         color = color.tinted();
                     } else if render_target.semi_renderable {
                         color = color.darkened();
+                    } else {
+                        color = light.shine_on(color);
                     }
                     self.rendering_context
                         .set_fill_style(&(color.to_string().into()));
@@ -136,6 +179,8 @@ impl<'a> System<'a> for Rendering {
                         color = color.tinted();
                     } else if render_target.semi_renderable {
                         color = color.darkened();
+                    } else {
+                        color = light.shine_on(color);
                     }
                     self.rendering_context
                         .set_fill_style(&(color.to_string().into()));
@@ -0,0 +1,150 @@
+use specs::prelude::*;
+
+use crate::game::{
+    components::{
+        initiative::Initiative,
+        status_effects::{StatusEffectKind, StatusEffects, HASTE_SPEED_MULTIPLIER},
+    },
+    world::{GameOver, WorldPosition, WorldTime},
+};
+
+/// Advances the world by as many turns as needed for someone to be able to act.
+///
+/// Nothing happens while any entity still has enough energy to act: the player is waiting for
+/// input, or a fast creature gets to act again before the turn ends. Only entities on the
+/// player's level take turns, the others are stowed without a position. Hasted entities gain
+/// energy faster. Time stops for good once the game is over.
+pub struct Scheduling {}
+
+impl<'a> System<'a> for Scheduling {
+    type SystemData = (
+        ReadStorage<'a, WorldPosition>,
+        WriteStorage<'a, Initiative>,
+        ReadStorage<'a, StatusEffects>,
+        Write<'a, WorldTime>,
+        Read<'a, GameOver>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            world_position,
+            mut initiative,
+            status_effects,
+            mut world_time,
+            game_over,
+        ): Self::SystemData,
+    ) {
+        if game_over.is_over() {
+            return;
+        }
+        if !(&initiative, &world_position)
+            .join()
+            .any(|(initiative, _)| initiative.speed > 0)
+        {
+            return;
+        }
+
+        while !(&initiative, &world_position)
+            .join()
+            .any(|(initiative, _)| initiative.is_ready())
+        {
+            world_time.tick += 1;
+            for (initiative, _, status_effects) in
+                (&mut initiative, &world_position, status_effects.maybe()).join()
+            {
+                let speed = match status_effects {
+                    Some(status_effects) if status_effects.has(StatusEffectKind::Haste) => {
+                        initiative.speed * HASTE_SPEED_MULTIPLIER
+                    }
+                    _ => initiative.speed,
+                };
+                initiative.energy += speed as i32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+
+    use crate::game::{
+        common::UIEvent,
+        components::behaviour::AIState,
+        test_harness::TestWorld,
+        world::{WorldPosition, WorldTime},
+    };
+
+    fn tick(test_world: &TestWorld) -> u64 {
+        test_world.simulation.world.read_resource::<WorldTime>().tick
+    }
+
+    #[test]
+    fn time_only_passes_when_the_player_acts() {
+        let mut test_world = TestWorld::new(10, 10);
+        test_world.player(5, 5);
+
+        test_world.send(vec![]);
+        test_world.send(vec![]);
+        assert_eq!(tick(&test_world), 1);
+
+        test_world.press(UIEvent::Left);
+        test_world.send(vec![]);
+        assert_eq!(tick(&test_world), 2);
+    }
+
+    #[test]
+    fn fast_monsters_act_twice_per_turn() {
+        let mut test_world = TestWorld::new(20, 3);
+        let player = test_world.player(0, 1);
+        let monster = test_world.monster(19, 1, 10, 1);
+        test_world.set_speed(monster, 200);
+        test_world.set_state(
+            monster,
+            AIState::Hunting {
+                last_known_position: WorldPosition { x: 0, y: 1 },
+            },
+        );
+
+        test_world.send(vec![]);
+        test_world.send(vec![]);
+        assert_eq!(test_world.position(monster), Some(WorldPosition { x: 17, y: 1 }));
+
+        test_world.press(UIEvent::Up);
+        assert_eq!(test_world.position(monster), Some(WorldPosition { x: 17, y: 1 }));
+        test_world.send(vec![]);
+        test_world.send(vec![]);
+        assert_eq!(test_world.position(monster), Some(WorldPosition { x: 15, y: 1 }));
+        assert_eq!(test_world.position(player), Some(WorldPosition { x: 0, y: 0 }));
+    }
+
+    #[test]
+    fn slow_monsters_skip_turns() {
+        let mut test_world = TestWorld::new(20, 3);
+        test_world.player(0, 1);
+        let monster = test_world.monster(19, 1, 10, 1);
+        test_world.set_speed(monster, 50);
+
+        for event in [UIEvent::Up, UIEvent::Down, UIEvent::Up, UIEvent::Down] {
+            test_world.press(event);
+        }
+        assert_eq!(tick(&test_world), 4);
+        assert_eq!(test_world.position(monster), Some(WorldPosition { x: 17, y: 1 }));
+    }
+
+    #[test]
+    fn moves_pressed_while_not_ready_are_kept() {
+        let mut test_world = TestWorld::new(20, 3);
+        let player = test_world.player(5, 1);
+        let monster = test_world.monster(19, 1, 10, 1);
+        test_world.set_speed(monster, 200);
+
+        test_world.press(UIEvent::Left);
+        // The monster is still acting, so the player has to wait for the next turn
+        test_world.press(UIEvent::Left);
+        assert_eq!(test_world.position(player), Some(WorldPosition { x: 4, y: 1 }));
+        test_world.send(vec![]);
+        assert_eq!(test_world.position(player), Some(WorldPosition { x: 3, y: 1 }));
+    }
+}
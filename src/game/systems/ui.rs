@@ -3,60 +3,139 @@ use std::sync::Arc;
 use specs::prelude::*;
 
 use crate::game::{
+    camera::Camera,
     components::{
-        damageable::Damageable, describable::Describable, player_controlled::PlayerControlled,
-        sighted::Sighted, parent::Parent,
+        damageable::Damageable, describable::Describable, equipped::Equipped,
+        inventoried::Inventoried, parent::Parent, pickupable::Pickupable,
+        player_controlled::PlayerControlled, sighted::Sighted, stackable::Stackable,
+        status_effects::StatusEffects,
     },
+    message_log::MessageLog,
+    random::Randomness,
+    spatial_index::SpatialIndex,
+    tile_map::TileMap,
     ui::game_ui::GameUI,
-    world::{UIState, WorldPosition, WorldPositionLookupTable}, hierarchy::Hierarchy,
+    world::{GameOver, UIState, WorldPosition},
 };
 
 pub struct UI {
     pub ui_state: Arc<GameUI>,
     pub last_mouse_over_position: Option<WorldPosition>,
-    pub past_children_bitset: Option<BitSet>
+    /// The inventory lines last shown, so the list is only rebuilt when they change.
+    pub past_inventory_lines: Vec<String>,
+    /// How many messages of the log were already shown.
+    pub messages_shown: usize,
 }
 
 impl<'a> System<'a> for UI {
     type SystemData = (
         Entities<'a>,
+        ReadExpect<'a, SpatialIndex>,
         ReadStorage<'a, PlayerControlled>,
         ReadStorage<'a, Damageable>,
         ReadStorage<'a, Describable>,
         ReadStorage<'a, Sighted>,
         Read<'a, UIState>,
-        Read<'a, WorldPositionLookupTable>,
-        ReadExpect<'a, Hierarchy<Parent>>,
+        Read<'a, Camera>,
+        Read<'a, Randomness>,
+        Read<'a, TileMap>,
+        ReadStorage<'a, Parent>,
+        ReadStorage<'a, Pickupable>,
+        ReadStorage<'a, Stackable>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, StatusEffects>,
+        Read<'a, GameOver>,
+        Read<'a, MessageLog>,
     );
 
     fn run(
         &mut self,
         (
             entities,
+            spatial_index,
             player_controlled,
             damageable,
             describable,
             sighted,
             ui_state,
-            world_position_lookup_table,
-            hierarchy
+            camera,
+            randomness,
+            tile_map,
+            parent,
+            pickupable,
+            stackable,
+            equipped,
+            status_effects,
+            game_over,
+            message_log,
         ): Self::SystemData,
     ) {
+        self.ui_state.seed.set_neq(randomness.seed);
+        self.ui_state
+            .player_death
+            .set_neq(game_over.player_death.clone());
+        if game_over.is_over() {
+            self.ui_state.player_health.set_neq(0);
+        }
+        if message_log.total() != self.messages_shown {
+            self.ui_state
+                .add_messages(message_log.since(self.messages_shown));
+            self.messages_shown = message_log.total();
+        }
+
         for (entity, _, damageable) in (&entities, &player_controlled, &damageable).join() {
             self.ui_state.player_health.set(damageable.health);
-            let new_child_bitset = hierarchy.all_children(entity);
-            let swap = self.past_children_bitset.clone().map(|b| b != new_child_bitset).unwrap_or(true);
-            if swap {
-                let mut lock = self.ui_state.inventory_entities.lock_mut();
-                lock.clear();
-                for (_, description) in (new_child_bitset.clone(), &describable).join() {
-                    lock.push_cloned(description.description.clone());
+            self.ui_state
+                .player_max_health
+                .set_neq(damageable.max_health);
+            let status = status_effects
+                .get(entity)
+                .map(|status_effects| {
+                    status_effects
+                        .effects
+                        .iter()
+                        .map(|effect| format!("{} ({})", effect.kind.adjective(), effect.turns))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            self.ui_state.player_status.set_neq(status);
+            let lines: Vec<String> = Inventoried::items(entity, &entities, &parent, &pickupable)
+                .into_iter()
+                .map(|item| {
+                    let mut line = describable
+                        .get(item)
+                        .map(|describable| describable.description.clone())
+                        .unwrap_or_default();
+                    if let Some(stackable) = stackable.get(item) {
+                        if stackable.count > 1 {
+                            line += &format!(" ×{}", stackable.count);
+                        }
+                    }
+                    if equipped.contains(item) {
+                        line += " (equipped)";
+                    }
+                    line
+                })
+                .collect();
+            if lines != self.past_inventory_lines {
+                if self
+                    .ui_state
+                    .selected_item
+                    .get()
+                    .is_some_and(|index| index >= lines.len())
+                {
+                    self.ui_state.selected_item.set(None);
                 }
-                self.past_children_bitset = new_child_bitset.clone().into();
+                self.ui_state
+                    .inventory_entities
+                    .lock_mut()
+                    .replace_cloned(lines.clone());
+                self.past_inventory_lines = lines;
             }
         }
 
-        let mouse_position = ui_state.mouse_over_position();
+        let mouse_position = ui_state.mouse_over_position(&camera);
         if mouse_position == self.last_mouse_over_position {
             return;
         }
@@ -66,19 +145,19 @@ impl<'a> System<'a> for UI {
 
         for (_, sighted) in (&player_controlled, &sighted).join() {
             if let Some(mouse_position) = mouse_position {
-                if let Some(entities) = world_position_lookup_table
-                    .world_position_entities
-                    .get(&mouse_position)
-                {
-                    for entity in entities {
-                        if !sighted.seen.contains(entity.id())
-                            && !sighted.seen_recently.contains(entity.id())
-                        {
-                            continue;
-                        }
-                        if let Some(description) = describable.get(*entity) {
-                            lock.push_cloned(description.description.clone());
-                        }
+                if tile_map.is_explored(mouse_position) {
+                    if let Some(tile) = tile_map.get(mouse_position) {
+                        lock.push_cloned(tile.description().to_owned());
+                    }
+                }
+                for entity in spatial_index.entities_at(mouse_position) {
+                    if !sighted.seen.contains(entity.id())
+                        && !sighted.seen_recently.contains(entity.id())
+                    {
+                        continue;
+                    }
+                    if let Some(description) = describable.get(entity) {
+                        lock.push_cloned(description.description.clone());
                     }
                 }
                 self.last_mouse_over_position = mouse_position.into();
@@ -0,0 +1,484 @@
+//! Hand-built worlds for testing the system pipeline.
+//!
+//! The level is marked as already generated so only the entities placed by the test exist, then
+//! tests push `UIEvent`s through the same `Simulation::tick` the frontend uses.
+
+use specs::prelude::*;
+
+use super::{
+    common::{Color, UIEvent},
+    components::{
+        ai_controlled::AIControlled,
+        armed::Armed,
+        behaviour::{AIState, Behaviour},
+        climbable::Climbable,
+        collidable::Collidable,
+        damageable::Damageable,
+        describable::Describable,
+        equippable::{EquipmentSlot, Equippable},
+        equipped::Equipped,
+        factioned::{Faction, Factioned},
+        initiative::{Initiative, NORMAL_SPEED},
+        inventoried::Inventoried,
+        level::Level,
+        light_source::LightSource,
+        movable::Movable,
+        parent::Parent,
+        pickupable::Pickupable,
+        player_controlled::PlayerControlled,
+        regenerating::Regenerating,
+        rendered::{Render, ZLayer},
+        sighted::Sighted,
+        stackable::Stackable,
+        status_effects::{StatusEffect, StatusEffectKind, StatusEffects},
+        usable::{ItemEffect, Usable},
+    },
+    hierarchy::Hierarchy,
+    message_log::MessageLog,
+    simulation::Simulation,
+    spatial_index::SpatialData,
+    tile_map::{Tile, TileMap},
+    world::{GameOver, PlayerDeath, WorldParameters, WorldPosition},
+};
+
+pub const TEST_SEED: u64 = 42;
+
+/// Accuracy of the test creatures, high enough that their attacks never miss.
+pub const SURE_HIT_ACCURACY: u32 = 10;
+
+pub struct TestWorld {
+    pub simulation: Simulation<'static, 'static>,
+}
+
+impl TestWorld {
+    pub fn new(width: u64, height: u64) -> TestWorld {
+        let mut simulation = Simulation::new(TEST_SEED, WorldParameters { width, height });
+        for level in (&mut simulation.world.write_storage::<Level>()).join() {
+            level.generated = true;
+        }
+        simulation
+            .world
+            .insert(TileMap::new(width, height, Tile::Floor));
+        TestWorld { simulation }
+    }
+
+    /// The level the player is on.
+    pub fn level(&self) -> Entity {
+        let entities = self.simulation.world.entities();
+        let level = self.simulation.world.read_storage::<Level>();
+        (&entities, &level)
+            .join()
+            .find(|(_, level)| level.active)
+            .map(|(entity, _)| entity)
+            .unwrap()
+    }
+
+    fn render(glyph: char, z_layer: ZLayer) -> Render {
+        Render {
+            glyph: glyph.into(),
+            foreground_color: Color::black(),
+            background_color: None,
+            z_layer,
+        }
+    }
+
+    fn on_level(
+        &mut self,
+        x: u64,
+        y: u64,
+        glyph: char,
+        z_layer: ZLayer,
+        description: &str,
+    ) -> EntityBuilder<'_> {
+        let level = self.level();
+        self.simulation
+            .world
+            .create_entity()
+            .with(Parent { entity: level })
+            .with(WorldPosition { x, y })
+            .with(TestWorld::render(glyph, z_layer))
+            .with(Describable {
+                description: description.to_owned(),
+            })
+    }
+
+    pub fn wall(&mut self, x: u64, y: u64) {
+        self.simulation
+            .world
+            .write_resource::<TileMap>()
+            .set(WorldPosition { x, y }, Tile::StoneWall);
+    }
+
+    pub fn tile(&self, x: u64, y: u64) -> Option<Tile> {
+        self.simulation
+            .world
+            .read_resource::<TileMap>()
+            .get(WorldPosition { x, y })
+    }
+
+    pub fn stairs(&mut self, x: u64, y: u64) -> Entity {
+        self.on_level(x, y, '>', ZLayer::Saturating, "Stairs Down")
+            .with(Climbable::Down)
+            .build()
+    }
+
+    pub fn up_stairs(&mut self, x: u64, y: u64) -> Entity {
+        self.on_level(x, y, '<', ZLayer::Saturating, "Stairs Up")
+            .with(Climbable::Up)
+            .build()
+    }
+
+    pub fn gold(&mut self, x: u64, y: u64) -> Entity {
+        self.on_level(x, y, '$', ZLayer::Item, "Gold")
+            .with(Pickupable)
+            .with(Stackable::new("gold"))
+            .build()
+    }
+
+    /// A scroll of magic mapping.
+    pub fn scroll(&mut self, x: u64, y: u64) -> Entity {
+        self.on_level(x, y, '?', ZLayer::Item, "Scroll")
+            .with(Pickupable)
+            .with(Stackable::new("scroll"))
+            .with(Usable {
+                effect: ItemEffect::RevealMap,
+            })
+            .build()
+    }
+
+    /// A potion healing `health`.
+    pub fn potion(&mut self, x: u64, y: u64, health: u32) -> Entity {
+        self.on_level(x, y, '!', ZLayer::Item, "Potion")
+            .with(Pickupable)
+            .with(Stackable::new("potion"))
+            .with(Usable {
+                effect: ItemEffect::Heal(health),
+            })
+            .build()
+    }
+
+    pub fn weapon(&mut self, x: u64, y: u64, damage: u32) -> Entity {
+        self.on_level(x, y, '/', ZLayer::Item, "Weapon")
+            .with(Pickupable)
+            .with(Equippable {
+                slot: EquipmentSlot::Weapon,
+                damage,
+                armor: 0,
+            })
+            .build()
+    }
+
+    /// A light with the strength of a torch.
+    pub fn light(&mut self, x: u64, y: u64, radius: u64) -> Entity {
+        self.on_level(x, y, '*', ZLayer::Item, "Light")
+            .with(LightSource {
+                radius,
+                color: Color::yellow(),
+                intensity: 1.0,
+            })
+            .build()
+    }
+
+    /// Leaves the level without any ambient light.
+    pub fn darken(&mut self) {
+        let level = self.level();
+        if let Some(level) = self
+            .simulation
+            .world
+            .write_storage::<Level>()
+            .get_mut(level)
+        {
+            level.ambient_light = 0.0;
+        }
+    }
+
+    pub fn monster(&mut self, x: u64, y: u64, health: u32, damage: u32) -> Entity {
+        self.on_level(x, y, 'm', ZLayer::Creature, "Monster")
+            .with(AIControlled)
+            .with(Behaviour::default())
+            .with(Sighted::default())
+            .with(Initiative::new(NORMAL_SPEED))
+            .with(Movable::default())
+            .with(Inventoried::default())
+            .with(Factioned {
+                faction: Faction::Enemy,
+            })
+            .with(Collidable)
+            .with(Damageable::new(health))
+            .with(Armed {
+                damage,
+                accuracy: SURE_HIT_ACCURACY,
+                ..Default::default()
+            })
+            .build()
+    }
+
+    pub fn player(&mut self, x: u64, y: u64) -> Entity {
+        self.simulation
+            .world
+            .create_entity()
+            .with(WorldPosition { x, y })
+            .with(TestWorld::render('@', ZLayer::Creature))
+            .with(PlayerControlled::default())
+            .with(Movable::default())
+            .with(Inventoried::default())
+            .with(Factioned {
+                faction: Faction::Player,
+            })
+            .with(Collidable)
+            .with(Damageable::new(100))
+            .with(Armed {
+                damage: 5,
+                accuracy: SURE_HIT_ACCURACY,
+                ..Default::default()
+            })
+            .with(Sighted::default())
+            .with(Initiative::new(NORMAL_SPEED))
+            .build()
+    }
+
+    pub fn teleport(&mut self, entity: Entity, x: u64, y: u64) {
+        self.simulation
+            .world
+            .write_storage::<WorldPosition>()
+            .insert(entity, WorldPosition { x, y })
+            .unwrap();
+    }
+
+    pub fn set_speed(&mut self, entity: Entity, speed: u32) {
+        self.simulation
+            .world
+            .write_storage::<Initiative>()
+            .insert(entity, Initiative::new(speed))
+            .unwrap();
+    }
+
+    /// Puts the item straight into the owner's inventory.
+    pub fn give(&mut self, item: Entity, owner: Entity) {
+        self.simulation
+            .world
+            .write_storage::<WorldPosition>()
+            .remove(item);
+        self.simulation
+            .world
+            .write_storage::<Parent>()
+            .insert(item, Parent { entity: owner })
+            .unwrap();
+    }
+
+    pub fn set_attack(&mut self, entity: Entity, accuracy: u32, critical_chance: f64) {
+        if let Some(armed) = self
+            .simulation
+            .world
+            .write_storage::<Armed>()
+            .get_mut(entity)
+        {
+            armed.accuracy = accuracy;
+            armed.critical_chance = critical_chance;
+        }
+    }
+
+    pub fn set_defenses(&mut self, entity: Entity, defenses: Damageable) {
+        self.simulation
+            .world
+            .write_storage::<Damageable>()
+            .insert(entity, defenses)
+            .unwrap();
+    }
+
+    pub fn set_regeneration(&mut self, entity: Entity, turns_per_point: u32) {
+        self.simulation
+            .world
+            .write_storage::<Regenerating>()
+            .insert(entity, Regenerating::new(turns_per_point))
+            .unwrap();
+    }
+
+    /// Gives the entity's attacks an effect to put on the targets they hit.
+    pub fn set_inflicts(&mut self, entity: Entity, effect: StatusEffect) {
+        if let Some(armed) = self
+            .simulation
+            .world
+            .write_storage::<Armed>()
+            .get_mut(entity)
+        {
+            armed.inflicts = Some(effect);
+        }
+    }
+
+    pub fn afflict(&mut self, entity: Entity, effect: StatusEffect) {
+        StatusEffects::afflict(
+            &mut self.simulation.world.write_storage::<StatusEffects>(),
+            entity,
+            effect,
+        );
+    }
+
+    pub fn is_under(&self, entity: Entity, kind: StatusEffectKind) -> bool {
+        self.simulation
+            .world
+            .read_storage::<StatusEffects>()
+            .get(entity)
+            .is_some_and(|status_effects| status_effects.has(kind))
+    }
+
+    pub fn set_state(&mut self, entity: Entity, state: AIState) {
+        if let Some(behaviour) = self
+            .simulation
+            .world
+            .write_storage::<Behaviour>()
+            .get_mut(entity)
+        {
+            behaviour.state = state;
+        }
+    }
+
+    pub fn state(&self, entity: Entity) -> Option<AIState> {
+        self.simulation
+            .world
+            .read_storage::<Behaviour>()
+            .get(entity)
+            .map(|behaviour| behaviour.state)
+    }
+
+    pub fn send(&mut self, events: Vec<UIEvent>) {
+        self.simulation.tick(events);
+    }
+
+    pub fn press(&mut self, event: UIEvent) {
+        self.send(vec![event]);
+    }
+
+    /// Steps the player back and forth, one turn per step.
+    pub fn pass_turns(&mut self, turns: usize) {
+        for turn in 0..turns {
+            let step = if turn % 2 == 0 {
+                UIEvent::Up
+            } else {
+                UIEvent::Down
+            };
+            self.press(step);
+            self.send(vec![]);
+        }
+    }
+
+    pub fn energy(&self, entity: Entity) -> i32 {
+        self.simulation
+            .world
+            .read_storage::<Initiative>()
+            .get(entity)
+            .map(|initiative| initiative.energy)
+            .unwrap_or(0)
+    }
+
+    pub fn stack_count(&self, entity: Entity) -> Option<u32> {
+        self.simulation
+            .world
+            .read_storage::<Stackable>()
+            .get(entity)
+            .map(|stackable| stackable.count)
+    }
+
+    pub fn is_equipped(&self, entity: Entity) -> bool {
+        self.simulation
+            .world
+            .read_storage::<Equipped>()
+            .contains(entity)
+    }
+
+    /// Whether the player knows what the cell is made of.
+    pub fn explored(&self, x: u64, y: u64) -> bool {
+        self.simulation
+            .world
+            .read_resource::<TileMap>()
+            .is_explored(WorldPosition { x, y })
+    }
+
+    pub fn description(&self, entity: Entity) -> Option<String> {
+        self.simulation
+            .world
+            .read_storage::<Describable>()
+            .get(entity)
+            .map(|describable| describable.description.clone())
+    }
+
+    /// The text of every message in the log, oldest first.
+    pub fn messages(&self) -> Vec<String> {
+        self.simulation
+            .world
+            .read_resource::<MessageLog>()
+            .messages()
+            .map(|message| message.text.clone())
+            .collect()
+    }
+
+    pub fn player_death(&self) -> Option<PlayerDeath> {
+        self.simulation
+            .world
+            .read_resource::<GameOver>()
+            .player_death
+            .clone()
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.simulation.world.entities().is_alive(entity)
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<WorldPosition> {
+        self.simulation
+            .world
+            .read_storage::<WorldPosition>()
+            .get(entity)
+            .copied()
+    }
+
+    pub fn health(&self, entity: Entity) -> Option<u32> {
+        self.simulation
+            .world
+            .read_storage::<Damageable>()
+            .get(entity)
+            .map(|d| d.health)
+    }
+
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.simulation
+            .world
+            .read_storage::<Parent>()
+            .get(entity)
+            .map(|p| p.entity)
+    }
+
+    pub fn children(&self, entity: Entity) -> Vec<Entity> {
+        self.simulation
+            .world
+            .read_resource::<Hierarchy<Parent>>()
+            .children(entity)
+            .to_vec()
+    }
+
+    pub fn sees(&self, viewer: Entity, entity: Entity) -> bool {
+        self.simulation
+            .world
+            .read_storage::<Sighted>()
+            .get(viewer)
+            .map(|sighted| sighted.seen.contains(entity.id()))
+            .unwrap_or(false)
+    }
+
+    /// Whether the player sees the cell, whatever is on it.
+    pub fn sees_position(&self, x: u64, y: u64) -> bool {
+        self.simulation
+            .world
+            .read_resource::<TileMap>()
+            .is_visible(WorldPosition { x, y })
+    }
+
+    pub fn entities_at(&self, x: u64, y: u64) -> Vec<Entity> {
+        self.simulation
+            .world
+            .system_data::<SpatialData>()
+            .index()
+            .entities_at(WorldPosition { x, y })
+            .collect()
+    }
+}
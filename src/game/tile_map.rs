@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    common::Color,
+    components::rendered::{Render, ZLayer},
+    world::WorldPosition,
+};
+
+/// Terrain, which unlike entities doesn't do anything on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tile {
+    Floor,
+    StoneWall,
+}
+
+impl Tile {
+    pub const ALL: [Tile; 2] = [Tile::Floor, Tile::StoneWall];
+
+    /// The name vault legends refer to the tile by.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tile::Floor => "floor",
+            Tile::StoneWall => "stone_wall",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Tile> {
+        Tile::ALL.iter().copied().find(|tile| tile.name() == name)
+    }
+
+    pub fn is_walkable(&self) -> bool {
+        match self {
+            Tile::Floor => true,
+            Tile::StoneWall => false,
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        match self {
+            Tile::Floor => true,
+            Tile::StoneWall => false,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Tile::Floor => "Floor",
+            Tile::StoneWall => "Stone Wall",
+        }
+    }
+
+    pub fn render(&self) -> Render {
+        match self {
+            Tile::Floor => Render {
+                glyph: '.'.into(),
+                foreground_color: Color::mildew(),
+                background_color: Color::brown().into(),
+                z_layer: ZLayer::Ground,
+            },
+            Tile::StoneWall => Render {
+                glyph: '#'.into(),
+                foreground_color: Color::brown(),
+                background_color: Color::black().into(),
+                z_layer: ZLayer::Saturating,
+            },
+        }
+    }
+}
+
+/// The terrain of the level the player is on, one tile per cell.
+///
+/// The tile map of every other level is kept in its `Level` until the player goes back to it.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TileMap {
+    width: u64,
+    height: u64,
+    tiles: Vec<Tile>,
+    /// Cells the player has seen at some point, which are drawn from memory once out of sight.
+    explored: Vec<bool>,
+    /// Cells the player sees right now. Worked out again every turn, so it isn't saved.
+    #[serde(skip)]
+    visible: Vec<bool>,
+    /// Goes up every time a tile changes, so that what was worked out from the tiles can be kept
+    /// until then.
+    #[serde(skip)]
+    revision: u64,
+}
+
+impl TileMap {
+    pub fn new(width: u64, height: u64, tile: Tile) -> TileMap {
+        let size = (width * height) as usize;
+        TileMap {
+            width,
+            height,
+            tiles: vec![tile; size],
+            explored: vec![false; size],
+            visible: vec![false; size],
+            revision: 0,
+        }
+    }
+
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// `None` outside of the map.
+    pub fn get(&self, world_position: WorldPosition) -> Option<Tile> {
+        self.index(world_position).map(|index| self.tiles[index])
+    }
+
+    pub fn set(&mut self, world_position: WorldPosition, tile: Tile) {
+        if let Some(index) = self.index(world_position) {
+            self.tiles[index] = tile;
+            self.revision += 1;
+        }
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Cells outside of the map can't be walked on.
+    pub fn is_walkable(&self, world_position: WorldPosition) -> bool {
+        self.get(world_position)
+            .is_some_and(|tile| tile.is_walkable())
+    }
+
+    /// Cells outside of the map can't be seen through.
+    pub fn is_transparent(&self, world_position: WorldPosition) -> bool {
+        self.get(world_position)
+            .is_some_and(|tile| tile.is_transparent())
+    }
+
+    pub fn is_explored(&self, world_position: WorldPosition) -> bool {
+        self.index(world_position)
+            .is_some_and(|index| self.explored[index])
+    }
+
+    pub fn is_visible(&self, world_position: WorldPosition) -> bool {
+        self.index(world_position)
+            .is_some_and(|index| self.visible.get(index).copied().unwrap_or(false))
+    }
+
+    /// Replaces what the player sees, and remembers it as explored.
+    pub fn set_visible(&mut self, world_positions: impl Iterator<Item = WorldPosition>) {
+        self.visible = vec![false; self.tiles.len()];
+        for world_position in world_positions {
+            if let Some(index) = self.index(world_position) {
+                self.visible[index] = true;
+                self.explored[index] = true;
+            }
+        }
+    }
+
+    /// Remembers the whole map as if it had been seen.
+    pub fn explore_all(&mut self) {
+        self.explored = vec![true; self.tiles.len()];
+    }
+
+    /// Every cell of the map, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = WorldPosition> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| WorldPosition { x, y }))
+    }
+
+    fn index(&self, world_position: WorldPosition) -> Option<usize> {
+        if world_position.x < self.width && world_position.y < self.height {
+            Some((world_position.y * self.width + world_position.x) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::world::WorldPosition;
+
+    use super::{Tile, TileMap};
+
+    #[test]
+    fn tiles_outside_of_the_map_block_everything() {
+        let mut tile_map = TileMap::new(3, 2, Tile::Floor);
+        tile_map.set(WorldPosition { x: 1, y: 1 }, Tile::StoneWall);
+
+        assert!(tile_map.is_walkable(WorldPosition { x: 2, y: 1 }));
+        assert!(!tile_map.is_walkable(WorldPosition { x: 1, y: 1 }));
+        assert!(!tile_map.is_transparent(WorldPosition { x: 1, y: 1 }));
+        assert!(!tile_map.is_walkable(WorldPosition { x: 3, y: 0 }));
+        assert!(!tile_map.is_transparent(WorldPosition { x: 0, y: 2 }));
+        assert_eq!(tile_map.positions().count(), 6);
+        assert_eq!(Tile::from_name("stone_wall"), Some(Tile::StoneWall));
+    }
+
+    #[test]
+    fn seen_tiles_stay_explored() {
+        let mut tile_map = TileMap::new(3, 3, Tile::Floor);
+        let (a, b) = (WorldPosition { x: 0, y: 0 }, WorldPosition { x: 2, y: 2 });
+
+        tile_map.set_visible([a].into_iter());
+        tile_map.set_visible([b].into_iter());
+
+        assert!(!tile_map.is_visible(a));
+        assert!(tile_map.is_explored(a));
+        assert!(tile_map.is_visible(b));
+        assert!(tile_map.is_explored(b));
+    }
+}
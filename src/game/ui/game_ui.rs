@@ -1,21 +1,76 @@
-use dominator::{clone, html, Dom};
+use dominator::{clone, events, html, Dom};
+use futures::channel::mpsc::UnboundedSender;
+use futures_signals::map_ref;
 use futures_signals::signal::{Mutable, SignalExt};
 use futures_signals::signal_vec::{MutableVec, SignalVecExt};
 
 use std::sync::Arc;
 
+use crate::game::{
+    common::UIEvent,
+    message_log::{Message, MessageKind, MAX_MESSAGES},
+    world::PlayerDeath,
+};
+
 pub struct GameUI {
+    pub seed: Mutable<u64>,
     pub player_health: Mutable<u32>,
+    pub player_max_health: Mutable<u32>,
+    /// The effects the player is under and the turns they have left.
+    pub player_status: Mutable<String>,
     pub inspected_entities: MutableVec<String>,
     pub inventory_entities: MutableVec<String>,
+    /// Place in the inventory of the item the item buttons act on.
+    pub selected_item: Mutable<Option<usize>>,
+    pub player_death: Mutable<Option<PlayerDeath>>,
+    /// Newest first.
+    pub messages: MutableVec<Message>,
+    /// Set by the restart button, the frontend starts a new game when it sees it.
+    pub restart_requested: Mutable<bool>,
+    events: UnboundedSender<UIEvent>,
 }
 
 impl GameUI {
-    pub fn new() -> Arc<Self> {
+    pub fn new(events: UnboundedSender<UIEvent>) -> Arc<Self> {
         Arc::new(Self {
+            seed: Mutable::new(0),
             player_health: Mutable::new(0),
+            player_max_health: Mutable::new(0),
+            player_status: Mutable::new(String::new()),
             inspected_entities: MutableVec::new(),
             inventory_entities: MutableVec::new(),
+            selected_item: Mutable::new(None),
+            player_death: Mutable::new(None),
+            messages: MutableVec::new(),
+            restart_requested: Mutable::new(false),
+            events,
+        })
+    }
+
+    /// Puts the messages, oldest first, on top of the log.
+    pub fn add_messages<'m>(&self, messages: impl Iterator<Item = &'m Message>) {
+        let mut lock = self.messages.lock_mut();
+        for message in messages {
+            lock.insert_cloned(0, message.clone());
+        }
+        lock.truncate(MAX_MESSAGES);
+    }
+
+    /// Sends the command for the selected item, if there is one.
+    pub fn command_selected_item(&self, command: fn(usize) -> UIEvent) {
+        if let Some(index) = self.selected_item.get() {
+            self.events.unbounded_send(command(index)).unwrap();
+        }
+    }
+
+    fn item_button(state: &Arc<Self>, label: &str, command: fn(usize) -> UIEvent) -> Dom {
+        html!("button", {
+            .class("item-command")
+            .text(label)
+            .prop_signal("disabled", state.selected_item.signal().map(|index| index.is_none()))
+            .event(clone!(state => move |_: events::Click| {
+                state.command_selected_item(command);
+            }))
         })
     }
 
@@ -29,10 +84,51 @@ impl GameUI {
                 }),
             ])
 
+            .children(&mut [
+                html!("div", {
+                    .class("game-over")
+                    .visible_signal(state.player_death.signal_ref(|death| death.is_some()))
+                    .children(&mut [
+                        html!("div", {
+                            .class("cause-of-death")
+                            .text_signal(state.player_death.signal_ref(|death| match death {
+                                Some(death) => format!(
+                                    "You died on depth {} after {} turns. {}.",
+                                    death.depth, death.tick, death.cause
+                                ),
+                                None => String::new(),
+                            }))
+                        }),
+                        html!("button", {
+                            .class("restart")
+                            .text("Restart")
+                            .event(clone!(state => move |_: events::Click| {
+                                state.restart_requested.set(true);
+                            }))
+                        }),
+                    ])
+                }),
+            ])
+
             .children(&mut [
                 html!("div", {
                     .class("player-health")
-                    .text_signal(state.player_health.signal().map(|x| format!("Player Health: {}", x)))
+                    .text_signal(map_ref! {
+                        let health = state.player_health.signal(),
+                        let max_health = state.player_max_health.signal() =>
+                        format!("Player Health: {}/{}", health, max_health)
+                    })
+                }),
+                html!("div", {
+                    .class("player-status")
+                    .text_signal(state.player_status.signal_cloned())
+                }),
+            ])
+
+            .children(&mut [
+                html!("div", {
+                    .class("seed")
+                    .text_signal(state.seed.signal().map(|x| format!("Seed: {}", x)))
                 }),
             ])
 
@@ -53,19 +149,64 @@ impl GameUI {
                 html!("hr", {})
             ])
 
+            .children(&mut [
+                html!("ul", {
+                    .class("message-log")
+                    .children_signal_vec(state.messages.signal_vec_cloned()
+                        .map(|message| {
+                            html!("li", {
+                                .class("message")
+                                .class(match message.kind {
+                                    MessageKind::Info => "message-info",
+                                    MessageKind::Combat => "message-combat",
+                                    MessageKind::Danger => "message-danger",
+                                    MessageKind::Loot => "message-loot",
+                                    MessageKind::Travel => "message-travel",
+                                })
+                                .text(&message.text)
+                            })
+                        })
+                    )
+                }),
+            ])
+
+            .children(&mut [
+                html!("hr", {})
+            ])
+
             .children(&mut [
                 html!("ul", {
                     .class("inventory-result")
                     .children_signal_vec(state.inventory_entities.signal_vec_cloned()
-                        .map(clone!(state => move |description| {
+                        .enumerate()
+                        .map(clone!(state => move |(index, description)| {
                             html!("li", {
                                 .class("inventoried")
+                                .class_signal("selected", map_ref! {
+                                    let index = index.signal(),
+                                    let selected = state.selected_item.signal() =>
+                                    index.is_some() && index == selected
+                                })
                                 .text(&description)
+                                .event(clone!(state => move |_: events::Click| {
+                                    state.selected_item.set(index.get());
+                                }))
                             })
                         }))
                     )
                 }),
             ])
+
+            .children(&mut [
+                html!("div", {
+                    .class("item-commands")
+                    .children(&mut [
+                        GameUI::item_button(state, "Use", UIEvent::UseItem),
+                        GameUI::item_button(state, "Drop", UIEvent::DropItem),
+                        GameUI::item_button(state, "Equip", UIEvent::EquipItem),
+                    ])
+                }),
+            ])
         })
     }
 }
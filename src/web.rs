@@ -0,0 +1,350 @@
+use crate::game::{
+    common::{CanvasPosition, CanvasSize, UIEvent},
+    components::parent::Parent,
+    hierarchy::Hierarchy,
+    replay::Replay,
+    simulation::Simulation,
+    snapshot::Snapshot,
+    systems::{rendering::Rendering, ui::UI},
+    ui::game_ui::GameUI,
+    world::WorldParameters,
+};
+use futures::{channel::mpsc, future, stream::select, StreamExt};
+use gloo_timers::future::IntervalStream;
+use log::{info, warn};
+use specs::prelude::*;
+use std::{cell::RefCell, panic, sync::Arc};
+use wasm_bindgen::prelude::*;
+
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+#[wasm_bindgen]
+extern "C" {
+    fn alert(string: &str);
+}
+
+/// The size of every level, which the camera scrolls across.
+const WORLD_PARAMETERS: WorldParameters = WorldParameters {
+    width: 100,
+    height: 100,
+};
+
+thread_local! {
+    static SIMULATION: RefCell<Option<Simulation<'static, 'static>>> = const { RefCell::new(None) };
+    static FRONTEND: RefCell<Option<Frontend>> = const { RefCell::new(None) };
+}
+
+/// What is needed to add the frontend systems to a new simulation.
+struct Frontend {
+    game_ui: Arc<GameUI>,
+    rendering_context: CanvasRenderingContext2d,
+    canvas_size: CanvasSize,
+}
+
+impl Frontend {
+    fn add_systems<'a, 'b>(&self, builder: DispatcherBuilder<'a, 'b>) -> DispatcherBuilder<'a, 'b> {
+        builder
+            .with(
+                UI {
+                    ui_state: self.game_ui.clone(),
+                    last_mouse_over_position: None,
+                    past_inventory_lines: vec![],
+                    messages_shown: 0,
+                },
+                "ui",
+                &["perspective", "messages", "framing"],
+            )
+            .with(
+                Rendering {
+                    canvas_size: self.canvas_size,
+                    rendering_context: self.rendering_context.clone(),
+                },
+                "rendering",
+                &["perspective", "framing"],
+            )
+    }
+}
+
+struct CanvasHandle {
+    context: CanvasRenderingContext2d,
+    canvas: HtmlCanvasElement,
+    actual_canvas_size: CanvasSize,
+}
+
+fn init_canvas_handle(size: CanvasSize) -> CanvasHandle {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let canvas = document
+        .get_elements_by_class_name("game-canvas")
+        .item(0)
+        .unwrap();
+    canvas
+        .set_attribute("width", &(size.width).to_string())
+        .unwrap();
+    canvas
+        .set_attribute("height", &(size.height).to_string())
+        .unwrap();
+
+    let canvas: web_sys::HtmlCanvasElement =
+        canvas.dyn_into::<web_sys::HtmlCanvasElement>().unwrap();
+
+    let context = canvas
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<web_sys::CanvasRenderingContext2d>()
+        .unwrap();
+
+    let actual_canvas_size = CanvasSize {
+        width: canvas.offset_width() as f64,
+        height: canvas.offset_height() as f64,
+    };
+
+    CanvasHandle {
+        context,
+        canvas,
+        actual_canvas_size,
+    }
+}
+
+/// Serializes the running game, to be handed back to `load_game` or `start`.
+#[wasm_bindgen]
+pub fn save_game() -> Option<String> {
+    SIMULATION.with(|simulation| {
+        simulation
+            .borrow()
+            .as_ref()
+            .map(|simulation| simulation.save().to_json())
+    })
+}
+
+#[wasm_bindgen]
+pub fn load_game(snapshot: &str) -> Result<(), JsValue> {
+    let snapshot = Snapshot::from_json(snapshot).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    SIMULATION.with(|simulation| match simulation.borrow_mut().as_mut() {
+        Some(simulation) => {
+            simulation.load(&snapshot);
+            Ok(())
+        }
+        None => Err(JsValue::from_str("The game has not started yet")),
+    })
+}
+
+/// Every input since the game was started or loaded, to be handed to `watch_replay`.
+#[wasm_bindgen]
+pub fn export_replay() -> Option<String> {
+    SIMULATION.with(|simulation| {
+        simulation
+            .borrow()
+            .as_ref()
+            .map(|simulation| simulation.recording().to_json())
+    })
+}
+
+/// Restarts the game where the replay started and plays it back, after which the game goes on
+/// with live input.
+#[wasm_bindgen]
+pub fn watch_replay(replay: &str) -> Result<(), JsValue> {
+    let replay = Replay::from_json(replay).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let simulation = FRONTEND.with(|frontend| {
+        frontend.borrow().as_ref().map(|frontend| {
+            Simulation::from_replay_with_systems(&replay, |builder| frontend.add_systems(builder))
+        })
+    });
+    match simulation {
+        Some(simulation) => {
+            SIMULATION.with(|cell| *cell.borrow_mut() = Some(simulation));
+            Ok(())
+        }
+        None => Err(JsValue::from_str("The game has not started yet")),
+    }
+}
+
+/// Throws the running game away and starts a new one with a fresh seed.
+#[wasm_bindgen]
+pub fn restart_game() -> Result<(), JsValue> {
+    let seed = (js_sys::Math::random() * u32::MAX as f64) as u64;
+    info!("Seed: {}", seed);
+    let simulation = FRONTEND.with(|frontend| {
+        frontend.borrow().as_ref().map(|frontend| {
+            frontend.game_ui.messages.lock_mut().clear();
+            Simulation::with_systems(seed, WORLD_PARAMETERS, |builder| {
+                frontend.add_systems(builder)
+            })
+        })
+    });
+    match simulation {
+        Some(simulation) => {
+            SIMULATION.with(|cell| *cell.borrow_mut() = Some(simulation));
+            Ok(())
+        }
+        None => Err(JsValue::from_str("The game has not started yet")),
+    }
+}
+
+#[wasm_bindgen]
+pub async fn start(seed: Option<u32>, snapshot: Option<String>) {
+    console_log::init_with_level(log::Level::Debug).unwrap();
+    panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let seed = seed.unwrap_or_else(|| (js_sys::Math::random() * u32::MAX as f64) as u32) as u64;
+    info!("Seed: {}", seed);
+
+    let (dx, rx) = mpsc::unbounded::<UIEvent>();
+
+    let game_ui = GameUI::new(dx.clone());
+    dominator::append_dom(&dominator::body(), GameUI::render(&game_ui));
+    let restart_requested = game_ui.restart_requested.clone();
+
+    let canvas_size = CanvasSize {
+        width: 1500.0,
+        height: 1500.0,
+    };
+    let canvas_handle = init_canvas_handle(canvas_size);
+
+    let frontend = Frontend {
+        game_ui: game_ui.clone(),
+        rendering_context: canvas_handle.context,
+        canvas_size,
+    };
+    let mut simulation = Simulation::with_systems(seed, WORLD_PARAMETERS, |builder| {
+        frontend.add_systems(builder)
+    });
+    FRONTEND.with(|cell| *cell.borrow_mut() = Some(frontend));
+
+    match snapshot.as_deref().map(Snapshot::from_json) {
+        Some(Ok(snapshot)) => simulation.load(&snapshot),
+        Some(Err(error)) => warn!("Starting a new game: {}", error),
+        None => {}
+    }
+
+    simulation.tick(vec![]);
+
+    let event_dispatcher = dx.clone();
+    let keyboard_handler = Closure::<dyn FnMut(_)>::new(move |e: web_sys::KeyboardEvent| {
+        let event: Option<UIEvent> = match e.key_code() {
+            37 => UIEvent::Left.into(),
+            38 => UIEvent::Up.into(),
+            39 => UIEvent::Right.into(),
+            40 => UIEvent::Down.into(),
+            // 1 to 9 pick an item, U, D and E use, drop and equip it
+            code @ 49..=57 => {
+                game_ui.selected_item.set(Some((code - 49) as usize));
+                None
+            }
+            85 => {
+                game_ui.command_selected_item(UIEvent::UseItem);
+                None
+            }
+            68 => {
+                game_ui.command_selected_item(UIEvent::DropItem);
+                None
+            }
+            69 => {
+                game_ui.command_selected_item(UIEvent::EquipItem);
+                None
+            }
+            _ => None,
+        };
+        if let Some(event) = event {
+            event_dispatcher.unbounded_send(event).unwrap();
+        }
+        e.prevent_default();
+    });
+
+    for entity in simulation.world.read_resource::<Hierarchy<Parent>>().all() {
+        println!("{:?}", entity);
+    }
+    SIMULATION.with(|cell| *cell.borrow_mut() = Some(simulation));
+
+    web_sys::window()
+        .unwrap()
+        .add_event_listener_with_callback("keydown", keyboard_handler.as_ref().unchecked_ref())
+        .unwrap();
+    keyboard_handler.forget();
+
+    let event_dispatcher = dx.clone();
+    let mouse_move_handler = Closure::<dyn FnMut(_)>::new(move |e: web_sys::MouseEvent| {
+        event_dispatcher
+            .unbounded_send(UIEvent::MouseOver(CanvasPosition::from_real(
+                CanvasPosition {
+                    x: e.offset_x() as f64,
+                    y: e.offset_y() as f64,
+                },
+                canvas_handle.actual_canvas_size,
+                canvas_size,
+            )))
+            .unwrap();
+        e.prevent_default();
+    });
+
+    canvas_handle
+        .canvas
+        .add_event_listener_with_callback("mousemove", mouse_move_handler.as_ref().unchecked_ref())
+        .unwrap();
+    mouse_move_handler.forget();
+
+    let event_dispatcher = dx.clone();
+    let mouse_down_handler = Closure::<dyn FnMut(_)>::new(move |e: web_sys::MouseEvent| {
+        event_dispatcher
+            .unbounded_send(UIEvent::MousePress(CanvasPosition::from_real(
+                CanvasPosition {
+                    x: e.offset_x() as f64,
+                    y: e.offset_y() as f64,
+                },
+                canvas_handle.actual_canvas_size,
+                canvas_size,
+            )))
+            .unwrap();
+        e.prevent_default();
+    });
+
+    canvas_handle
+        .canvas
+        .add_event_listener_with_callback("mousedown", mouse_down_handler.as_ref().unchecked_ref())
+        .unwrap();
+    mouse_down_handler.forget();
+
+    let event_dispatcher = dx.clone();
+    let mouse_leave_handler = Closure::<dyn FnMut(_)>::new(move |e: web_sys::MouseEvent| {
+        event_dispatcher
+            .unbounded_send(UIEvent::MouseLeave)
+            .unwrap();
+        e.prevent_default();
+    });
+
+    canvas_handle
+        .canvas
+        .add_event_listener_with_callback(
+            "mouseleave",
+            mouse_leave_handler.as_ref().unchecked_ref(),
+        )
+        .unwrap();
+    mouse_leave_handler.forget();
+
+    let render_request_stream = IntervalStream::new(16).map(|_| None);
+    let events_stream = select(rx.map(|e: UIEvent| Some(e)), render_request_stream);
+    let mut events_since_last_render: Vec<UIEvent> = vec![];
+
+    events_stream
+        .for_each(move |event| {
+            match event {
+                None => {
+                    if restart_requested.replace(false) {
+                        events_since_last_render.clear();
+                        restart_game().unwrap();
+                    }
+                    let events = events_since_last_render.drain(..).collect();
+                    SIMULATION.with(|simulation| {
+                        if let Some(simulation) = simulation.borrow_mut().as_mut() {
+                            simulation.tick(events);
+                        }
+                    });
+                }
+                Some(event) => {
+                    events_since_last_render.push(event);
+                }
+            }
+            future::ready(())
+        })
+        .await;
+}